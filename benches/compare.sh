@@ -0,0 +1,23 @@
+#!/bin/sh
+# Run the geometry bench on an older revision and on the working tree, side by side:
+#   benches/compare.sh [REV] [CARGO ARGS...]
+# REV defaults to HEAD~1. The working tree's bench is used for both, so only the library differs.
+set -e
+rev=${1:-HEAD~1}
+[ $# -gt 0 ] && shift
+root=$(git rev-parse --show-toplevel)
+tree=$(mktemp -d)
+trap 'git -C "$root" worktree remove --force "$tree"' EXIT
+
+git -C "$root" worktree add --detach "$tree" "$rev" >/dev/null 2>&1
+mkdir -p "$tree/benches"
+cp "$root/benches/geometry.rs" "$tree/benches/geometry.rs"
+grep -q '^name = "geometry"' "$tree/Cargo.toml" || printf '\n[[bench]]\nname = "geometry"\nharness = false\n' >> "$tree/Cargo.toml"
+
+# One target directory, dependencies are only built once
+export CARGO_TARGET_DIR="$root/target"
+(cd "$tree" && cargo bench --bench geometry "$@" 2>/dev/null) > "$tree/before.txt"
+(cd "$root" && cargo bench --bench geometry "$@" 2>/dev/null) > "$tree/after.txt"
+
+printf '%-24s %12s %12s\n' "" "$rev" "working tree"
+awk -F '  +' 'NR == FNR { before[$1] = $2; next } $1 in before { printf "%-24s %12s %12s\n", $1, before[$1], $2 }' "$tree/before.txt" "$tree/after.txt"
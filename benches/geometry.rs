@@ -0,0 +1,82 @@
+// Animation and rendering hot paths. No harness, `cargo bench --bench geometry` prints the median time per
+// iteration. Only API that predates the geometry types is used, so `benches/compare.sh` can run the same
+// bench on an older revision.
+use std::{hint::black_box, time::{Duration, Instant}};
+
+use hyogen_ui::{
+    animator::animator::Animator,
+    hvf::{hvf::HVF, loader::HVFLoader},
+    layout::Layout,
+    renderer::{memory::MemoryBackend, raster::SoftwareRenderer, Color, Frame, Style},
+};
+
+// A face of `paths` circles with `points` points each, `wobble` makes expressions differ.
+fn face(paths: usize, points: usize, wobble: f64) -> String {
+    let rings: Vec<String> = (0..paths).map(|i| {
+        let points: Vec<String> = (0..points).map(|j| {
+            let angle = std::f64::consts::TAU * j as f64 / points as f64;
+            let radius = 40.0 + wobble * (angle * 3.0).sin();
+            format!("[{:.3}, {:.3}]", 100.0 + 150.0 * i as f64 + radius * angle.cos(), 240.0 + radius * angle.sin())
+        }).collect();
+        format!("[{}]", points.join(", "))
+    }).collect();
+    format!("[{}]", rings.join(", "))
+}
+
+// Rounds timed per bench, the median is reported.
+const ROUNDS: usize = 9;
+
+fn bench(name: &str, iterations: u32, mut run: impl FnMut()) {
+    // Warm up, then time
+    for _ in 0..iterations / 10 {
+        run();
+    }
+    let mut rounds: Vec<Duration> = (0..ROUNDS).map(|_| {
+        let start = Instant::now();
+        for _ in 0..iterations {
+            run();
+        }
+        start.elapsed() / iterations
+    }).collect();
+    rounds.sort();
+    println!("{:<24} {:>10.2?}", name, rounds[ROUNDS / 2]);
+}
+
+fn main() {
+    let pack = format!(r#"{{"expression": {{"neutral": {}, "happy": {}}}}}"#, face(5, 64, 0.0), face(5, 96, 8.0));
+    let loader = HVFLoader::parse(&pack).unwrap();
+    let neutral = loader.get("expression", "neutral").unwrap();
+    let happy = loader.get("expression", "happy").unwrap();
+
+    bench("parse", 200, || {
+        black_box(HVFLoader::parse(black_box(&pack)).unwrap());
+    });
+
+    // Resampling and aligning 64 to 96 points, per path
+    let mut animator = Animator::new(neutral);
+    let start = Instant::now();
+    bench("animate (normalize)", 2_000, || {
+        animator.animate_at(black_box(happy), Duration::from_secs(1), start);
+    });
+
+    let mut frame = 0;
+    bench("sample", 20_000, || {
+        frame += 1;
+        black_box(animator.get_path(start + Duration::from_micros(frame % 1_000_000)));
+    });
+
+    bench("blend", 2_000, || {
+        black_box(Animator::blend(&[(neutral as &HVF, 1.0), (happy, 1.0)]));
+    });
+
+    let mut renderer = SoftwareRenderer::new();
+    let mut backend = MemoryBackend::new(800, 480);
+    let transform = Layout::default().transform(800, 480, 1.0);
+    let style = Style { fill: Some(Color::WHITE), ..Style::default() };
+    bench("sample and render", 500, || {
+        frame += 1;
+        let paths = animator.get_path(start + Duration::from_micros(frame % 1_000_000));
+        let frame = Frame::new(paths, vec![style], Color::BLACK).with_transform(transform);
+        backend.draw(&mut renderer, &frame);
+    });
+}
@@ -1,17 +1,23 @@
 use core::f64;
-use std::time::{Duration, Instant};
+use std::{collections::VecDeque, time::{Duration, Instant}};
 
-use num_integer::gcd;
-
-use crate::hvf::hvf::HVF;
-use super::interpolator::Interpolator;
+use crate::{events::Event, geometry::{perimeter, Point, Shape}, hvf::hvf::HVF};
+use super::{easing::Easing, interpolator::Interpolator};
 
 pub struct Animator {
     active_ring: HVF,                          // Current active ring (state of animation)
+    target_ring: HVF,                          // Ring the current animation ends on
     active_interpolators: Vec<Interpolator>,   // Collection of interpolators for animation
+    queue: VecDeque<(HVF, Duration, Easing)>,  // Steps played once the current animation completes
 
     interpolate_instance: Instant,             // Time animation started
-    interpolate_duration: Duration             // Total duration of the animation
+    interpolate_duration: Duration,            // Total duration of the animation
+    easing: Easing,                            // Curve of the current animation
+    looping: bool,                             // Restart the animation once it completes
+    paused_at: Option<Instant>,                // Time stands still from here until resumed
+    settling: bool,                            // Started an animation that hasn't been reported as settled
+    interruptions: u64,                        // Animations cut short by a new one
+    events: Vec<Event>,                        // Posted, waiting for `take_events`
 }
 
 impl Animator {
@@ -19,44 +25,23 @@ impl Animator {
         // Initialize animator with a default ring
         Self {
             active_ring: default_ring.clone(),
+            target_ring: default_ring.clone(),
             active_interpolators: Vec::new(),
+            queue: VecDeque::new(),
             interpolate_instance: Instant::now(),
             interpolate_duration: Duration::from_secs_f64(0.0),
+            easing: Easing::Linear,
+            looping: false,
+            paused_at: None,
+            settling: false,
+            interruptions: 0,
+            events: Vec::new(),
         }
     }
 
-    fn get_path_len(&self, path: &Vec<Vec<f64>>) -> f64 {
-        // Calculate the total length of a closed path
-        debug_assert!(path.len() > 1, "Path must contain at least two points");
-        let mut path_len = 0.0;
-        let mut prev = path.last().unwrap(); // Wraps to last point for closure
-        for point in path {
-            let dx = prev[0] - point[0];
-            let dy = prev[1] - point[1];
-            path_len += (dx * dx + dy * dy).sqrt();
-            prev = point;
-        }
-        path_len
-    }
-
-    fn get_distance(&self, a: &Vec<f64>, b: &Vec<f64>) -> f64 {
-        // Calculate the Euclidean distance between two points
-        debug_assert!(a.len() == 2 && b.len() == 2, "Points must be 2D vectors");
-        if a == b { return 0.0; } // Early return if identical
-        ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
-    }
-
-    fn get_point_along(&self, a: &Vec<f64>, b: &Vec<f64>, pct: f64) -> Vec<f64> {
-        // Get a point along a segment at a given percentage
-        let pct = pct.clamp(0.0, 1.0);
-        if pct == 0.0 { return a.clone(); }
-        if pct == 1.0 { return b.clone(); }
-        vec![a[0] + (b[0] - a[0]) * pct, a[1] + (b[1] - a[1]) * pct]
-    }
-
-    fn add_points(&mut self, path: &mut Vec<Vec<f64>>, size: usize) {
+    fn add_points(&mut self, path: &mut Vec<Point>, size: usize) {
         // Add evenly spaced points to match the desired path size
-        let total_len = self.get_path_len(path);
+        let total_len = perimeter(path);
         let step = total_len / size as f64;
         let mut new_path = Vec::with_capacity(path.len() + size);
         let mut cursor = 0.0;
@@ -64,20 +49,20 @@ impl Animator {
 
         let mut i = 0;
         while new_path.len() < path.len() + size {
-            let a = &path[i];
-            let b = &path[(i + 1) % path.len()];
-            let segment = self.get_distance(a, b);
+            let a = path[i];
+            let b = path[(i + 1) % path.len()];
+            let segment = a.distance(b);
 
             if insert_at <= cursor + segment {
                 if segment != 0.0 {
                     let t = (insert_at - cursor) / segment;
-                    new_path.push(self.get_point_along(a, b, t));
+                    new_path.push(a.lerp(b, t.clamp(0.0, 1.0)));
                 } else {
-                    new_path.push(a.clone());
+                    new_path.push(a);
                 }
                 insert_at += step;
             } else {
-                new_path.push(a.clone());
+                new_path.push(a);
                 cursor += segment;
                 i = (i + 1) % path.len();
             }
@@ -86,26 +71,7 @@ impl Animator {
         *path = new_path;
     }
 
-    fn offset_path(&mut self, path: &mut Vec<Vec<f64>>, path_len: usize, offset_val: usize) {
-        // Offset the path cyclically by a given amount
-        let offset_val = offset_val % path_len;
-        if offset_val == 0 { return; } // No rotation needed
-        let g_c_d = gcd(offset_val, path_len);
-
-        for i in 0..g_c_d {
-            let temp = std::mem::take(&mut path[i]);
-            let mut j = i;
-            loop {
-                let k = (j + offset_val) % path_len;
-                if k == i { break; }
-                path[j] = std::mem::replace(&mut path[k], Vec::new());
-                j = k;
-            }
-            path[j] = temp;
-        }
-    }
-
-    fn rotate(&mut self, path: &mut Vec<Vec<f64>>, vs: &[Vec<f64>]) {
+    fn rotate(&mut self, path: &mut [Point], vs: &[Point]) {
         // Align the path with the target using the smallest squared distance
         let path_len = vs.len();
         let mut min_val = f64::INFINITY;
@@ -113,10 +79,7 @@ impl Animator {
 
         for offset in 0..path_len {
             let sum_of_square: f64 = (0..path_len)
-                .map(|i| {
-                    let d = self.get_distance(&path[(offset + i) % path_len], &vs[i]);
-                    d * d
-                })
+                .map(|i| path[(offset + i) % path_len].distance_squared(vs[i]))
                 .sum();
 
             if sum_of_square < min_val {
@@ -126,12 +89,10 @@ impl Animator {
             }
         }
 
-        if best_offset != 0 {
-            self.offset_path(path, path_len, best_offset);
-        }
+        path.rotate_left(best_offset);
     }
 
-    fn normalize(&mut self, current_path: &mut Vec<Vec<f64>>, target_path: &mut Vec<Vec<f64>>) {
+    fn normalize(&mut self, current_path: &mut Vec<Point>, target_path: &mut Vec<Point>) {
         // Normalize two paths to have the same number of points and alignment
         let diff = current_path.len() as i32 - target_path.len() as i32;
 
@@ -145,34 +106,211 @@ impl Animator {
     }
 
     fn get_interpolaror(&mut self, target_ring: &HVF) -> Vec<Interpolator> {
-        // Create interpolators for animating paths
+        // Create interpolators for animating paths. Rings with a different number of them can't morph, callers
+        // are expected to check `HVF::morphs_into`, and anything that slips through jumps to the target.
+        let jump = !self.active_ring.morphs_into(target_ring);
         let mut collection = Vec::new();
-        for (i, path) in target_ring.values().iter().enumerate() {
-            let current_path = &mut self.active_ring.values()[i].clone();
-            let target_path = &mut path.clone();
-            self.normalize(current_path, target_path);
-            collection.push(Interpolator::new(current_path.to_owned(), target_path.to_owned()));
+        for (i, path) in target_ring.values().paths().enumerate() {
+            let mut current_path = match jump {
+                true => path.to_vec(),
+                false => self.active_ring.values()[i].to_vec(),
+            };
+            let mut target_path = path.to_vec();
+            self.normalize(&mut current_path, &mut target_path);
+            collection.push(Interpolator::new(current_path, target_path));
         }
         collection
     }
 
     pub fn animate(&mut self, target_ring: &HVF, duration: Duration) {
         // Initialize animation with a target ring and duration
-        self.interpolate_instance = Instant::now();
+        self.animate_at(target_ring, duration, Instant::now());
+    }
+
+    pub fn animate_at(&mut self, target_ring: &HVF, duration: Duration, start: Instant) {
+        // Same as animate, but starting at the given instant (used for virtual clocks)
+        self.animate_with(target_ring, duration, Easing::Linear, start);
+    }
+
+    pub fn animate_with(&mut self, target_ring: &HVF, duration: Duration, easing: Easing, start: Instant) {
+        // While paused the new animation waits at its first frame until resumed
+        let start = self.clock(start);
+
+        if self.looping || start < self.ends_at() {
+            self.interruptions += 1;
+        }
+
+        // Start from wherever the face currently is, so interrupting an animation doesn't jump
+        if !self.active_interpolators.is_empty() {
+            let mut paths = Shape::new();
+            self.sample(start, &mut paths);
+            self.active_ring = HVF::from_paths(paths);
+        }
+        self.queue.clear();
+        self.start_step(target_ring, duration, easing, start);
+    }
+
+    pub fn enqueue(&mut self, target_ring: &HVF, duration: Duration) {
+        // Play an animation once the current one (and everything queued before it) completes
+        self.enqueue_with(target_ring, duration, Easing::Linear);
+    }
+
+    pub fn enqueue_with(&mut self, target_ring: &HVF, duration: Duration, easing: Easing) {
+        self.queue.push_back((target_ring.clone(), duration, easing));
+        self.settling = true;
+    }
+
+    // How many times an animation was replaced before it finished playing, queued steps included. Whoever
+    // waits for one to finish can tell it never will.
+    pub fn interruptions(&self) -> u64 {
+        self.interruptions
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(now);
+            self.events.push(Event::Paused);
+        }
+    }
+
+    pub fn resume(&mut self, now: Instant) {
+        // Shift the animation by however long it was paused, so it continues where it stopped
+        if let Some(paused_at) = self.paused_at.take() {
+            self.interpolate_instance += now.saturating_duration_since(paused_at);
+            self.events.push(Event::Resumed);
+        }
+    }
+
+    // Events posted since the last call, including `Settled` if everything finished playing by `now`.
+    pub fn take_events(&mut self, now: Instant) -> Vec<Event> {
+        if self.settling && !self.is_paused() && self.is_settled(now) {
+            self.settling = false;
+            self.events.push(Event::Settled);
+        }
+        std::mem::take(&mut self.events)
+    }
+
+    // When `take_events` will have `Settled` to report.
+    pub fn next_event_at(&self) -> Option<Instant> {
+        self.settles_at().filter(|_| self.settling)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    fn clock(&self, time: Instant) -> Instant {
+        self.paused_at.map_or(time, |paused_at| paused_at.min(time))
+    }
+
+    // Mix expressions by weight. They're folded in one at a time, each taking its share of the weight so far.
+    // None if there's nothing to mix, or the expressions don't have the same number of paths.
+    pub fn blend(weighted: &[(&HVF, f64)]) -> Option<HVF> {
+        let (first, first_weight) = weighted.first()?;
+        if weighted.iter().any(|(ring, _)| !first.morphs_into(ring)) {
+            return None;
+        }
+        let mut blended = (*first).clone();
+        let mut total = first_weight.max(0.0);
+
+        for (ring, weight) in &weighted[1..] {
+            let weight = weight.max(0.0);
+            total += weight;
+            if weight == 0.0 {
+                continue;
+            }
+
+            let mut mixer = Animator::new(&blended);
+            let mut paths = Shape::new();
+            for interpolator in mixer.get_interpolaror(ring) {
+                interpolator.interpolate_into(weight / total, &mut paths);
+            }
+            blended = HVF::from_paths(paths);
+        }
+        Some(blended)
+    }
+
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn target_ring(&self) -> &HVF {
+        &self.target_ring
+    }
+
+    pub fn is_settled(&self, time: Instant) -> bool {
+        // True once the last animation completed, queued ones included, nothing changes while paused either
+        self.is_paused() || self.settles_at().is_some_and(|at| time >= at)
+    }
+
+    pub fn settles_at(&self) -> Option<Instant> {
+        // When everything queued will have played, None if it never settles
+        if self.looping || self.is_paused() {
+            return None;
+        }
+        Some(self.ends_at())
+    }
+
+    // When everything queued will have played, pauses aside.
+    fn ends_at(&self) -> Instant {
+        let queued: Duration = self.queue.iter().map(|(_, duration, _)| *duration).sum();
+        self.interpolate_instance + self.interpolate_duration + queued
+    }
+
+    fn start_step(&mut self, target_ring: &HVF, duration: Duration, easing: Easing, start: Instant) {
+        self.interpolate_instance = start;
         self.interpolate_duration = duration;
+        self.easing = easing;
         self.active_interpolators = self.get_interpolaror(target_ring);
+        self.target_ring = target_ring.clone();
+        self.settling = true;
     }
 
-    pub fn get_path(&mut self, time: Instant) -> Vec<Vec<Vec<f64>>> {
-        // Get the current animation state based on elapsed time
-        let mut paths = Vec::new();
-        let elapsed = time.duration_since(self.interpolate_instance);
-        let interpolate_value = (elapsed.as_secs_f64() / self.interpolate_duration.as_secs_f64()) % 1.0;
+    fn advance(&mut self, time: Instant) {
+        // Start every queued step whose predecessor completed before `time`
+        while !self.looping && !self.queue.is_empty() && self.progress(time) >= 1.0 {
+            let start = self.interpolate_instance + self.interpolate_duration;
+            let (target_ring, duration, easing) = self.queue.pop_front().unwrap();
 
-        for interpolator in self.active_interpolators.iter_mut() {
-            paths.push(interpolator.interpolate(interpolate_value));
+            self.active_ring = self.target_ring.clone();
+            self.start_step(&target_ring, duration, easing, start);
         }
+    }
+
+    fn progress(&self, time: Instant) -> f64 {
+        let elapsed = time.saturating_duration_since(self.interpolate_instance);
+        if self.interpolate_duration.is_zero() {
+            1.0
+        } else if self.looping {
+            (elapsed.as_secs_f64() / self.interpolate_duration.as_secs_f64()) % 1.0
+        } else {
+            (elapsed.as_secs_f64() / self.interpolate_duration.as_secs_f64()).min(1.0)
+        }
+    }
+
+    fn sample(&self, time: Instant, shape: &mut Shape) {
+        let interpolate_value = self.easing.apply(self.progress(time));
+        shape.clear();
+        for interpolator in &self.active_interpolators {
+            interpolator.interpolate_into(interpolate_value, shape);
+        }
+    }
+
+    pub fn get_path(&mut self, time: Instant) -> Shape {
+        // Get the current animation state based on elapsed time
+        let mut shape = Shape::new();
+        self.get_path_into(time, &mut shape);
+        shape
+    }
 
-        paths
+    pub fn get_path_into(&mut self, time: Instant, shape: &mut Shape) {
+        // Same as get_path, reusing the memory of `shape` so drawing a frame doesn't allocate
+        let time = self.clock(time);
+        self.advance(time);
+        self.sample(time, shape);
     }
 }
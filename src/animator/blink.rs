@@ -1,19 +1,73 @@
+use std::time::{Duration, Instant};
+
 use crate::hvf::hvf::HVF;
 
 use super::animator::Animator;
 
 pub struct Blink {
-
+    blink_ring: HVF,
+    interval: Duration,        // Time between two blinks
+    close: Duration,           // Time it takes to close the eyes
+    open: Duration,            // Time it takes to open them again
+    next_at: Instant,
 }
 
 impl Blink {
-    pub fn new(blink_hvf: &HVF, animator: &Animator) -> Self {
+    pub fn new(blink_hvf: &HVF, interval: Duration) -> Self {
         Self {
+            blink_ring: blink_hvf.clone(),
+            interval,
+            close: Duration::from_millis(100),
+            open: Duration::from_millis(150),
+            next_at: Instant::now() + interval,
+        }
+    }
+
+    pub fn set_timing(&mut self, interval: Duration, close: Duration, open: Duration) {
+        self.next_at = self.next_at.min(Instant::now() + interval);
+        self.interval = interval;
+        self.close = close;
+        self.open = open;
+    }
+
+    pub fn set_ring(&mut self, blink_hvf: &HVF) {
+        self.blink_ring = blink_hvf.clone();
+    }
 
+    pub fn next_at(&self) -> Instant {
+        self.next_at
+    }
+
+    // Blink right away, e.g. when asked to over the control socket. Returns false if the face is busy.
+    pub fn trigger(&mut self, now: Instant, animator: &mut Animator) -> bool {
+        let next_at = self.next_at;
+        self.next_at = now;
+        let blinked = self.poll(now, animator);
+        if !blinked {
+            self.next_at = next_at;
         }
+        blinked
     }
 
-    pub fn start(&self) {
-        
+    pub fn poll(&mut self, now: Instant, animator: &mut Animator) -> bool {
+        // Blink if it's time to, returns true if an animation was started
+        if now < self.next_at {
+            return false;
+        }
+        self.next_at = now + self.interval;
+
+        // Don't interrupt other animations, just wait for the next blink
+        if animator.is_paused() || !animator.is_settled(now) {
+            return false;
+        }
+        // Nor try to morph into closed eyes drawn with a different number of shapes
+        if !animator.target_ring().morphs_into(&self.blink_ring) {
+            return false;
+        }
+
+        let rest_ring = animator.target_ring().clone();
+        animator.animate_at(&self.blink_ring, self.close, now);
+        animator.enqueue(&rest_ring, self.open);
+        true
     }
-}
\ No newline at end of file
+}
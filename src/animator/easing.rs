@@ -0,0 +1,62 @@
+use std::{f64::consts::PI, str::FromStr};
+
+// Timing curve applied to the progress of a transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    InCubic,
+    OutCubic,
+    InOutCubic,
+    InOutSine,
+}
+
+impl Easing {
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::InQuad => t * t,
+            Easing::OutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::InOutQuad => if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 },
+            Easing::InCubic => t * t * t,
+            Easing::OutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::InOutCubic => if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 },
+            Easing::InOutSine => -((PI * t).cos() - 1.0) / 2.0,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Easing::Linear => "linear",
+            Easing::InQuad => "in-quad",
+            Easing::OutQuad => "out-quad",
+            Easing::InOutQuad => "in-out-quad",
+            Easing::InCubic => "in-cubic",
+            Easing::OutCubic => "out-cubic",
+            Easing::InOutCubic => "in-out-cubic",
+            Easing::InOutSine => "in-out-sine",
+        }
+    }
+}
+
+impl FromStr for Easing {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "linear" => Ok(Easing::Linear),
+            "in-quad" => Ok(Easing::InQuad),
+            "out-quad" => Ok(Easing::OutQuad),
+            "in-out-quad" => Ok(Easing::InOutQuad),
+            "in-cubic" => Ok(Easing::InCubic),
+            "out-cubic" => Ok(Easing::OutCubic),
+            "in-out-cubic" => Ok(Easing::InOutCubic),
+            "in-out-sine" => Ok(Easing::InOutSine),
+            _ => Err(format!("Unknown easing {}", value)),
+        }
+    }
+}
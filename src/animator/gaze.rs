@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use super::easing::Easing;
+
+// How far the face moves when looking all the way to a side, in design units.
+const GAZE_REACH: f64 = 24.0;
+
+// Where the face looks, from (-1, -1) top-left to (1, 1) bottom-right. The whole face shifts towards it.
+pub struct Gaze {
+    from: (f64, f64),
+    to: (f64, f64),
+    start: Instant,
+    duration: Duration,
+    easing: Easing,
+}
+
+impl Default for Gaze {
+    fn default() -> Self {
+        Self {
+            from: (0.0, 0.0),
+            to: (0.0, 0.0),
+            start: Instant::now(),
+            duration: Duration::ZERO,
+            easing: Easing::default(),
+        }
+    }
+}
+
+impl Gaze {
+    // Look towards a target, starting from wherever the gaze is right now.
+    pub fn look_at(&mut self, target: (f64, f64), duration: Duration, easing: Easing, now: Instant) {
+        self.from = self.direction(now);
+        self.to = (target.0.clamp(-1.0, 1.0), target.1.clamp(-1.0, 1.0));
+        self.start = now;
+        self.duration = duration;
+        self.easing = easing;
+    }
+
+    pub fn target(&self) -> (f64, f64) {
+        self.to
+    }
+
+    pub fn direction(&self, now: Instant) -> (f64, f64) {
+        let t = match self.duration.is_zero() {
+            true => 1.0,
+            false => now.saturating_duration_since(self.start).as_secs_f64() / self.duration.as_secs_f64(),
+        };
+        let t = self.easing.apply(t);
+        (self.from.0 + (self.to.0 - self.from.0) * t, self.from.1 + (self.to.1 - self.from.1) * t)
+    }
+
+    // Shift of the face in design units.
+    pub fn offset(&self, now: Instant) -> (f64, f64) {
+        let (x, y) = self.direction(now);
+        (x * GAZE_REACH, y * GAZE_REACH)
+    }
+
+    pub fn is_settled(&self, now: Instant) -> bool {
+        now >= self.start + self.duration
+    }
+}
@@ -1,35 +1,23 @@
-pub struct Interpolator {
-    current_path: Vec<Vec<f64>>,
-    target_path: Vec<Vec<f64>>,
+use crate::geometry::{Point, Shape};
 
-    target_size: usize,
+pub struct Interpolator {
+    current_path: Vec<Point>,
+    target_path: Vec<Point>,       // Same number of points as `current_path`, see `Animator::normalize`
 }
 
 impl Interpolator {
-    pub fn new(current_path: Vec<Vec<f64>>, target_path: Vec<Vec<f64>>) -> Self {
-        let target_size = target_path.len();
+    pub fn new(current_path: Vec<Point>, target_path: Vec<Point>) -> Self {
+        debug_assert_eq!(current_path.len(), target_path.len(), "Paths must be normalized first");
 
         Self {
             current_path,
             target_path,
-            
-            target_size,
         }
     }
 
-    pub fn interpolate(&mut self, t: f64) -> Vec<Vec<f64>> {
-        let mut target: Vec<Vec<f64>> = Vec::new();
-
-        // Interpolation logic here
-        for i in 0..self.target_size {
-            let from_point = &self.current_path[i];
-            let to_point = &self.target_path[i];
-
-            let xt = from_point[0] + t * (to_point[0] - from_point[0]);
-            let yt = from_point[1] + t * (to_point[1] - from_point[1]);
-            target.push(vec![xt, yt]);
-        }
-
-        target
+    pub fn interpolate_into(&self, t: f64, shape: &mut Shape) {
+        // Append the path `t` of the way to the target to `shape`
+        let points = self.current_path.iter().zip(&self.target_path).map(|(from, to)| from.lerp(*to, t));
+        shape.push(points);
     }
 }
@@ -1,3 +1,6 @@
 pub mod blink;
+pub mod easing;
+pub mod gaze;
+#[allow(clippy::module_inception)]
 pub mod animator;
 pub mod interpolator;
\ No newline at end of file
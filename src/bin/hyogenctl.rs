@@ -0,0 +1,133 @@
+use hyogen_ui::control::client::{ClientError, ControlClient};
+use serde_json::{Map, Value};
+
+use std::{env, path::PathBuf, process};
+
+const USAGE: &str = "Usage: hyogenctl [--socket <path>] <command>
+
+Commands:
+  set <expression> [--duration 300ms] [--ease linear|in-quad|out-cubic|...]
+  blink
+  pause
+  resume
+  state [--json]
+  list
+  watch               Print events as JSON lines until hyogen-ui exits";
+
+// Build a request for the control socket from the command line.
+fn request(command: &str, args: &[String]) -> Result<Value, String> {
+    let mut request = Map::new();
+    request.insert("cmd".to_string(), Value::String(command.to_string()));
+
+    match command {
+        "set" => {
+            let mut args = args.iter();
+            let expression = args.next().filter(|arg| !arg.starts_with("--")).ok_or("set expects an expression")?;
+            request.insert("expression".to_string(), Value::String(expression.clone()));
+
+            while let Some(flag) = args.next() {
+                let value = args.next().ok_or_else(|| format!("{} expects a value", flag))?;
+                match flag.as_str() {
+                    "--duration" => request.insert("duration".to_string(), Value::String(value.clone())),
+                    "--ease" | "--easing" => request.insert("easing".to_string(), Value::String(value.clone())),
+                    _ => return Err(format!("Unknown option {}", flag)),
+                };
+            }
+        }
+        "state" => {
+            if let Some(arg) = args.iter().find(|arg| *arg != "--json") {
+                return Err(format!("Unknown option {}", arg));
+            }
+        }
+        "blink" | "pause" | "resume" | "list" | "watch" => {
+            if let Some(arg) = args.first() {
+                return Err(format!("{} takes no arguments, got {}", command, arg));
+            }
+        }
+        _ => return Err(format!("Unknown command {}", command)),
+    }
+
+    // Watching is just a subscription that never ends
+    if command == "watch" {
+        request.insert("cmd".to_string(), Value::String("subscribe".to_string()));
+    }
+    Ok(Value::Object(request))
+}
+
+fn run(client: &mut ControlClient, command: &str, args: &[String], request: &Value) -> Result<(), ClientError> {
+    let response = client.request(request)?;
+
+    match command {
+        "state" if args.iter().any(|arg| arg == "--json") => println!("{}", response["state"]),
+        "state" => {
+            let state = &response["state"];
+            let mut line = state["expression"].as_str().unwrap_or("unknown").to_string();
+            if state["paused"].as_bool() == Some(true) {
+                line.push_str(" (paused)");
+            } else if state["animating"].as_bool() == Some(true) {
+                line.push_str(" (animating)");
+            }
+            match state["queued"].as_u64() {
+                Some(queued) if queued > 0 => println!("{}, {} queued", line, queued),
+                _ => println!("{}", line),
+            }
+        }
+        "list" => {
+            for expression in response["expressions"].as_array().into_iter().flatten() {
+                if let Some(expression) = expression.as_str() {
+                    println!("{}", expression);
+                }
+            }
+        }
+        "watch" => {
+            while let Some(event) = client.next_event()? {
+                println!("{}", event);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    // The socket can be picked before the command
+    let mut socket = None;
+    if args.first().map(String::as_str) == Some("--socket") {
+        if args.len() < 2 {
+            eprintln!("--socket expects a path");
+            process::exit(2);
+        }
+        socket = Some(PathBuf::from(args.remove(1)));
+        args.remove(0);
+    }
+
+    let Some((command, args)) = args.split_first() else {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    };
+    if command == "help" || command == "--help" || command == "-h" {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let request = match request(command, args) {
+        Ok(request) => request,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let result = match &socket {
+        Some(path) => ControlClient::connect(path),
+        None => ControlClient::connect_default(),
+    }
+    .and_then(|mut client| run(&mut client, command, args, &request));
+
+    if let Err(err) = result {
+        eprintln!("hyogenctl: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,195 @@
+use std::path::PathBuf;
+
+use crate::config::{self, Config, ConfigError};
+
+pub const USAGE: &str = "Usage: hyogen-ui [run] [<hvf file>] [options]
+       hyogen-ui <command> [options]
+
+Commands:
+  run        Show the face on the compositor (the default)
+  render     Render frames of an animation to image files
+  export     Export an animation as a GIF or APNG
+  preview    Play an animation in the terminal
+  config     Check the config file and show the settings it results in
+  help       Show this help, or the help of a command
+
+Settings come from $XDG_CONFIG_HOME/hyogen/config.toml (or --config <file>), options override them.
+See `hyogen-ui help run` for the options.";
+
+pub const RUN_USAGE: &str = "Usage: hyogen-ui [run] [<hvf file>] [options]
+
+Options:
+  --config <file>            Config file to use instead of $XDG_CONFIG_HOME/hyogen/config.toml
+  --no-config                Don't read any config file
+  --class <class>            HVF class to take expressions from (expression)
+  --expression <name>        Expression to start with (neutral)
+  --fps-cap <fps>            Limit the frame rate, e.g. on battery
+  --layout contain|cover|stretch|fixed:<scale>
+  --align center|top|bottom-left|...
+  --padding <px>
+  --background RRGGBBAA
+  --layer background|bottom|top|overlay
+  --anchor top,left,...|all|none
+  --margin <px>|<top,right,bottom,left>
+  --size 800x480|0x0
+  --exclusive-zone <px>
+  --keyboard none|exclusive|on-demand
+  --namespace <name>
+  --output auto|all|<name>,...
+  --no-blink                 Don't blink on your own
+  --supervise                Reconnect when the compositor goes away
+  --window                   Use a regular window even if layer-shell is available
+  --control-socket <path>    Listen for commands here instead of $XDG_RUNTIME_DIR/hyogen-ui.sock
+  --no-control               Don't listen for commands on a socket
+  --no-dbus                  Don't offer org.osmos.Hyogen on the session bus
+  --stdin                    Take commands as JSON lines on stdin, print answers and events on stdout";
+
+pub const RENDER_USAGE: &str = "Usage: hyogen-ui render --hvf <file> --to <expression> [--from <expression>] [--duration 0.5] [--fps 60]
+                        [--size 800x480] [--out frames/] [--format png|ppm] [--background RRGGBBAA]";
+
+pub const EXPORT_USAGE: &str = "Usage: hyogen-ui export --hvf <file> (--to <expression> | --sequence neutral,happy:0.5,...) --out <file.gif|file.png>
+                        [--loop 0] [--scale 1.0] [--colors 256] [--quality 10]";
+
+pub const PREVIEW_USAGE: &str = "Usage: hyogen-ui preview --hvf <file> (--to <expression> | --sequence ...) [--mode braille|half] [--cells 80x24]
+                         [--fps 30] [--loop]";
+
+pub const CONFIG_USAGE: &str = "Usage: hyogen-ui config [--config <file>]";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cli {
+    Run(RunArgs),
+    Render(Vec<String>),            // The offline commands parse their own options
+    Export(Vec<String>),
+    Preview(Vec<String>),
+    Config(Option<PathBuf>),
+    Help(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Override {
+    Flag(String, String),
+    Switch(String),
+}
+
+// `run` options, kept as given until they're applied over the config file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunArgs {
+    pub config: Option<PathBuf>,
+    pub no_config: bool,
+    pub hvf: Option<PathBuf>,
+    pub stdin: bool,
+    overrides: Vec<Override>,
+}
+
+impl RunArgs {
+    // The config file in use, None without one.
+    pub fn config_path(&self) -> Option<PathBuf> {
+        match self.no_config {
+            true => None,
+            false => self.config.clone().or_else(config::find_config),
+        }
+    }
+
+    // Built-in defaults, overridden by the config file, overridden by the command line.
+    pub fn resolve(&self) -> Result<Config, ConfigError> {
+        let mut config = match self.config_path() {
+            Some(path) => Config::load(&path)?,
+            None => Config::default(),
+        };
+        self.apply(&mut config)?;
+        Ok(config)
+    }
+
+    // Errors name the flag at fault.
+    pub fn apply(&self, config: &mut Config) -> Result<(), ConfigError> {
+        if let Some(hvf) = &self.hvf {
+            config.hvf = vec![hvf.clone()];
+        }
+        for option in &self.overrides {
+            match option {
+                Override::Flag(flag, value) => {
+                    config.apply_flag(flag, value).map_err(|message| ConfigError::Invalid { key: flag.clone(), message })?;
+                }
+                Override::Switch(switch) => {
+                    config.apply_switch(switch);
+                }
+            }
+        }
+
+        // The config file's placement is valid on its own, so one of the flags made it invalid
+        config.placement.validate().map_err(|(key, message)| {
+            let flag = format!("--{}", key);
+            let given = |name: &str| self.overrides.iter().any(|option| matches!(option, Override::Flag(flag, _) if flag == name));
+            let key = match given(&flag) {
+                true => flag,
+                false if given("--anchor") => "--anchor".to_string(),
+                false => format!("placement.{}", key),
+            };
+            ConfigError::Invalid { key, message }
+        })
+    }
+}
+
+// Parse the arguments after the program name.
+pub fn parse(args: &[String]) -> Result<Cli, String> {
+    let Some((first, rest)) = args.split_first() else {
+        return parse_run(args);
+    };
+
+    let wants_help = |args: &[String]| args.iter().any(|arg| arg == "--help" || arg == "-h");
+    match first.as_str() {
+        "help" | "--help" | "-h" => Ok(Cli::Help(match rest.first().map(String::as_str) {
+            None => USAGE,
+            Some("run") => RUN_USAGE,
+            Some("render") => RENDER_USAGE,
+            Some("export") => EXPORT_USAGE,
+            Some("preview") => PREVIEW_USAGE,
+            Some("config") => CONFIG_USAGE,
+            Some(other) => return Err(format!("Unknown command {}", other)),
+        })),
+        "run" if wants_help(rest) => Ok(Cli::Help(RUN_USAGE)),
+        "run" => parse_run(rest),
+        "render" if wants_help(rest) => Ok(Cli::Help(RENDER_USAGE)),
+        "render" => Ok(Cli::Render(rest.to_vec())),
+        "export" if wants_help(rest) => Ok(Cli::Help(EXPORT_USAGE)),
+        "export" => Ok(Cli::Export(rest.to_vec())),
+        "preview" if wants_help(rest) => Ok(Cli::Help(PREVIEW_USAGE)),
+        "preview" => Ok(Cli::Preview(rest.to_vec())),
+        "config" if wants_help(rest) => Ok(Cli::Help(CONFIG_USAGE)),
+        "config" => match rest {
+            [] => Ok(Cli::Config(None)),
+            [flag, path] if flag == "--config" => Ok(Cli::Config(Some(PathBuf::from(path)))),
+            _ => Err(format!("Unexpected arguments {}", rest.join(" "))),
+        },
+        // `hyogen-ui face.hvf --window` like before there were commands
+        _ => parse_run(args),
+    }
+}
+
+fn parse_run(args: &[String]) -> Result<Cli, String> {
+    let mut run = RunArgs::default();
+
+    // Checked right away, so mistakes show up before anything is loaded
+    let mut check = Config::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--help" | "-h" => return Ok(Cli::Help(RUN_USAGE)),
+            "--config" => run.config = Some(PathBuf::from(iter.next().ok_or("--config expects a path")?)),
+            "--no-config" => run.no_config = true,
+            "--stdin" => run.stdin = true,
+            switch if check.apply_switch(switch) => run.overrides.push(Override::Switch(switch.to_string())),
+            flag if flag.starts_with("--") => {
+                let value = iter.next().ok_or_else(|| format!("{} expects a value", flag))?;
+                if !check.apply_flag(flag, value).map_err(|err| format!("{}: {}", flag, err))? {
+                    return Err(format!("Unknown option {}", flag));
+                }
+                run.overrides.push(Override::Flag(flag.to_string(), value.to_string()));
+            }
+            path if run.hvf.is_none() => run.hvf = Some(PathBuf::from(path)),
+            other => return Err(format!("Unexpected argument {}", other)),
+        }
+    }
+
+    Ok(Cli::Run(run))
+}
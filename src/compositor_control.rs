@@ -0,0 +1,172 @@
+use std::time::Instant;
+
+use wayland_client::{globals::GlobalList, Connection, Dispatch, QueueHandle};
+
+use crate::{
+    animator::{animator::Animator, easing::Easing},
+    control::{protocol::{Command, Transition}, ControlHandler},
+    headless,
+};
+
+use protocol::hyogen_control_v1::{self, HyogenControlV1};
+
+// Client side of protocols/hyogen-control-v1.xml.
+#[allow(non_upper_case_globals, clippy::all)]
+pub mod protocol {
+    use wayland_client;
+
+    pub mod __interfaces {
+        use wayland_client::backend as wayland_backend;
+        wayland_scanner::generate_interfaces!("protocols/hyogen-control-v1.xml");
+    }
+    use self::__interfaces::*;
+
+    wayland_scanner::generate_client_code!("protocols/hyogen-control-v1.xml");
+}
+
+// States that let the compositor drive the face, see `delegate_hyogen_control!`.
+pub trait CompositorControlHandler: ControlHandler {
+    fn compositor_control(&mut self) -> &mut CompositorControl;
+    // Where the commands end up, to tell when they're interrupted.
+    fn animator(&self) -> &Animator;
+}
+
+// Commands sent by Aurora through hyogen_control_v1, when it offers the global.
+pub struct CompositorControl {
+    control: Option<HyogenControlV1>,
+    pending: Vec<u32>,      // Serials to report as finished once the face settles
+    interruptions: u64,     // Of the animator, when the last serial was added
+}
+
+impl CompositorControl {
+    pub fn bind<D>(globals: &GlobalList, qh: &QueueHandle<D>) -> Self
+    where
+        D: Dispatch<HyogenControlV1, ()> + 'static,
+    {
+        let control = globals.bind::<HyogenControlV1, _, _>(qh, 1..=1, ()).ok();
+        if control.is_some() {
+            tracing::info!("The compositor can control the face");
+        }
+        Self { control, pending: Vec::new(), interruptions: 0 }
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.control.is_some()
+    }
+
+    // Report the commands that finished by now.
+    pub fn poll(&mut self, animator: &Animator, now: Instant) {
+        self.forget_interrupted(animator.interruptions());
+        let Some(control) = &self.control else {
+            return;
+        };
+        if self.pending.is_empty() || animator.is_paused() || !animator.is_settled(now) {
+            return;
+        }
+
+        for serial in self.pending.drain(..) {
+            control.finished(serial);
+        }
+    }
+
+    // When `poll` has something to report, if nothing else wakes us up before.
+    pub fn next_wakeup(&self, animator: &Animator) -> Option<Instant> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        animator.settles_at()
+    }
+
+    // A new expression interrupts whatever was playing, no matter who asked for it. Those commands are never
+    // finished.
+    fn forget_interrupted(&mut self, interruptions: u64) {
+        if interruptions != self.interruptions {
+            self.pending.clear();
+            self.interruptions = interruptions;
+        }
+    }
+
+    fn command<D: CompositorControlHandler>(state: &mut D, serial: u32, command: Command) {
+        let immediate = matches!(command, Command::Pause | Command::Resume);
+
+        let result = state.handle_command(command);
+        let interruptions = state.animator().interruptions();
+        let control = state.compositor_control();
+        let Some(proxy) = &control.control else {
+            return;
+        };
+
+        match result {
+            Ok(_) if immediate => proxy.finished(serial),
+            Ok(_) => {
+                control.forget_interrupted(interruptions);
+                control.pending.push(serial);
+            }
+            Err(err) => {
+                tracing::warn!("Rejected a command from the compositor: {}", err);
+                proxy.rejected(serial, err);
+            }
+        }
+    }
+}
+
+impl Drop for CompositorControl {
+    fn drop(&mut self) {
+        if let Some(control) = self.control.take() {
+            control.destroy();
+        }
+    }
+}
+
+fn transition(expression: String, duration: u32, easing: &str) -> Result<Transition, String> {
+    let easing = match easing {
+        "" => Easing::default(),
+        easing => easing.parse()?,
+    };
+    // Milliseconds, capped like durations from any other client
+    let duration = headless::duration_from_secs(duration as f64 / 1000.0)
+        .ok_or_else(|| format!("Invalid duration {}ms, at most {}s", duration, headless::MAX_DURATION.as_secs()))?;
+    Ok(Transition { expression, duration, easing })
+}
+
+impl<D> Dispatch<HyogenControlV1, (), D> for CompositorControl
+where
+    D: Dispatch<HyogenControlV1, ()> + CompositorControlHandler,
+{
+    fn event(
+            state: &mut D,
+            proxy: &HyogenControlV1,
+            event: hyogen_control_v1::Event,
+            _data: &(),
+            _conn: &Connection,
+            _qh: &QueueHandle<D>,
+        ) {
+        use hyogen_control_v1::Event;
+
+        let (serial, command) = match event {
+            Event::SetExpression { serial, expression, duration, easing } => (serial, transition(expression, duration, &easing).map(Command::Set)),
+            Event::EnqueueExpression { serial, expression, duration, easing } => {
+                let steps = transition(expression, duration, &easing).map(|step| vec![step]);
+                (serial, steps.map(|steps| Command::Sequence { steps, replace: false }))
+            }
+            Event::Blink { serial } => (serial, Ok(Command::Blink)),
+            Event::Pause { serial } => (serial, Ok(Command::Pause)),
+            Event::Resume { serial } => (serial, Ok(Command::Resume)),
+        };
+
+        match command {
+            Ok(command) => Self::command(state, serial, command),
+            Err(err) => proxy.rejected(serial, err),
+        }
+    }
+}
+
+// Route hyogen_control_v1 events of a state implementing `CompositorControlHandler` to `CompositorControl`.
+#[macro_export]
+macro_rules! delegate_hyogen_control {
+    ($ty:ty) => {
+        wayland_client::delegate_dispatch!($ty: [
+            $crate::compositor_control::protocol::hyogen_control_v1::HyogenControlV1: ()
+        ] => $crate::compositor_control::CompositorControl);
+    };
+}
@@ -0,0 +1,445 @@
+use std::{
+    env, fmt, fs, io, path::{Path, PathBuf}, time::Duration
+};
+
+use toml::{Table, Value};
+
+use crate::{
+    control, headless,
+    layout::Layout,
+    placement::{self, Placement},
+    renderer::{Color, Style},
+};
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid { key: String, message: String },     // `key` is the full dotted path, like placement.layer
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "Failed to read {}: {}", path.display(), err),
+            ConfigError::Parse(path, err) => write!(f, "Invalid TOML in {}: {}", path.display(), err),
+            ConfigError::Invalid { key, message } => write!(f, "{}: {}", key, message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlinkConfig {
+    pub enabled: bool,
+    pub interval: Duration,
+    pub close: Duration,
+    pub open: Duration,
+}
+
+impl Default for BlinkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: Duration::from_secs(4),
+            close: Duration::from_millis(100),
+            open: Duration::from_millis(150),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub style: Style,
+    pub background: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self { style: Style::default(), background: Color::TRANSPARENT }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpcConfig {
+    pub control: bool,                  // Listen on the control socket
+    pub socket: Option<PathBuf>,        // Where, `$XDG_RUNTIME_DIR/hyogen-ui.sock` if not set
+    pub dbus: bool,
+}
+
+impl Default for IpcConfig {
+    fn default() -> Self {
+        Self { control: true, socket: None, dbus: true }
+    }
+}
+
+impl IpcConfig {
+    pub fn socket_path(&self) -> Option<PathBuf> {
+        match self.control {
+            true => self.socket.clone().or_else(control::default_socket_path),
+            false => None,
+        }
+    }
+}
+
+// Everything hyogen-ui can be told how to run with. Built-in defaults, overridden by the config file,
+// overridden by command line flags.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub hvf: Vec<PathBuf>,              // The first one that loads is used
+    pub class: String,
+    pub expression: String,             // Shown at startup
+    pub placement: Placement,
+    pub layout: Layout,
+    pub blink: BlinkConfig,
+    pub theme: Theme,
+    pub fps_cap: Option<f64>,
+    pub ipc: IpcConfig,
+    pub supervise: bool,
+    pub window: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            hvf: Vec::new(),
+            class: "expression".to_string(),
+            expression: "neutral".to_string(),
+            placement: Placement::default(),
+            layout: Layout::default(),
+            blink: BlinkConfig::default(),
+            theme: Theme::default(),
+            fps_cap: None,
+            ipc: IpcConfig::default(),
+            supervise: false,
+            window: false,
+        }
+    }
+}
+
+// `$XDG_CONFIG_HOME/hyogen/config.toml` (`~/.config` by default), then the same in `$XDG_CONFIG_DIRS`.
+// None if there's no config file at all, which is fine.
+pub fn find_config() -> Option<PathBuf> {
+    let non_empty = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+
+    let user = non_empty("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".config")));
+    let system = non_empty("XDG_CONFIG_DIRS")
+        .map(|dirs| env::split_paths(&dirs).collect::<Vec<PathBuf>>())
+        .unwrap_or_else(|| vec![PathBuf::from("/etc/xdg")]);
+
+    user.into_iter()
+        .chain(system)
+        .map(|dir| dir.join("hyogen").join("config.toml"))
+        .find(|path| path.is_file())
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let mut config = Self::default();
+        config.merge_file(path)?;
+        Ok(config)
+    }
+
+    // Apply the settings of a config file on top of these. Relative paths in it are relative to the file.
+    pub fn merge_file(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let text = fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+        let table: Table = text.parse().map_err(|err| ConfigError::Parse(path.to_path_buf(), err))?;
+        self.merge(&table, path.parent().unwrap_or(Path::new(".")))
+    }
+
+    pub fn merge(&mut self, table: &Table, base: &Path) -> Result<(), ConfigError> {
+        let root = Section { prefix: String::new(), table };
+        root.only(&["face", "placement", "layout", "blink", "theme", "render", "ipc", "session"])?;
+
+        if let Some(face) = root.section("face")? {
+            face.only(&["hvf", "class", "expression"])?;
+            if let Some(hvf) = face.get("hvf") {
+                let paths = match hvf {
+                    Value::String(path) => vec![path.as_str()],
+                    Value::Array(paths) => paths.iter()
+                        .map(|path| path.as_str().ok_or_else(|| face.invalid("hvf", "Expected a path or a list of paths")))
+                        .collect::<Result<Vec<&str>, ConfigError>>()?,
+                    _ => return Err(face.invalid("hvf", "Expected a path or a list of paths")),
+                };
+                self.hvf = paths.into_iter().map(|path| resolve(base, path)).collect();
+            }
+            if let Some(class) = face.string("class")? {
+                self.class = class.to_string();
+            }
+            if let Some(expression) = face.string("expression")? {
+                self.expression = expression.to_string();
+            }
+        }
+
+        if let Some(section) = root.section("placement")? {
+            section.only(&["layer", "anchor", "margin", "size", "exclusive-zone", "keyboard", "namespace", "output"])?;
+            let placement = &mut self.placement;
+            if let Some(layer) = section.parse("layer", placement::parse_layer)? {
+                placement.layer = layer;
+            }
+            if let Some(anchor) = section.parse("anchor", placement::parse_anchor)? {
+                placement.anchor = anchor;
+            }
+            if let Some(margin) = section.parse("margin", str::parse)? {
+                placement.margin = margin;
+            }
+            if let Some(size) = section.parse("size", placement::parse_size)? {
+                placement.size = size;
+            }
+            if let Some(zone) = section.integer("exclusive-zone")? {
+                placement.exclusive_zone = i32::try_from(zone).map_err(|_| section.invalid("exclusive-zone", "Out of range"))?;
+            }
+            if let Some(keyboard) = section.parse("keyboard", placement::parse_keyboard)? {
+                placement.keyboard = keyboard;
+            }
+            if let Some(namespace) = section.string("namespace")? {
+                placement.namespace = namespace.to_string();
+            }
+            if let Some(outputs) = section.parse("output", str::parse)? {
+                placement.outputs = outputs;
+            }
+            placement.validate().map_err(|(key, message)| section.invalid(key, &message))?;
+        }
+
+        if let Some(layout) = root.section("layout")? {
+            layout.only(&["mode", "align", "padding"])?;
+            if let Some(mode) = layout.parse("mode", str::parse)? {
+                self.layout.mode = mode;
+            }
+            if let Some(align) = layout.parse("align", str::parse)? {
+                self.layout.align = align;
+            }
+            if let Some(padding) = layout.parse("padding", str::parse)? {
+                self.layout.padding = padding;
+            }
+        }
+
+        if let Some(blink) = root.section("blink")? {
+            blink.only(&["enabled", "interval", "close", "open"])?;
+            if let Some(enabled) = blink.boolean("enabled")? {
+                self.blink.enabled = enabled;
+            }
+            if let Some(interval) = blink.duration("interval")? {
+                if interval.is_zero() {
+                    return Err(blink.invalid("interval", "Must be longer than 0"));
+                }
+                self.blink.interval = interval;
+            }
+            if let Some(close) = blink.duration("close")? {
+                self.blink.close = close;
+            }
+            if let Some(open) = blink.duration("open")? {
+                self.blink.open = open;
+            }
+        }
+
+        if let Some(theme) = root.section("theme")? {
+            theme.only(&["outline", "stroke", "stroke-width", "fill", "background"])?;
+            if let Some(outline) = theme.parse("outline", str::parse)? {
+                self.theme.style.outline = outline;
+            }
+            if let Some(stroke) = theme.parse("stroke", parse_color)? {
+                self.theme.style.stroke = stroke;
+            }
+            if let Some(width) = theme.number("stroke-width")? {
+                if width <= 0.0 {
+                    return Err(theme.invalid("stroke-width", "Must be positive"));
+                }
+                self.theme.style.stroke_width = width;
+            }
+            match theme.get("fill") {
+                Some(Value::Boolean(false)) => self.theme.style.fill = None,
+                _ => if let Some(fill) = theme.parse("fill", parse_color)? {
+                    self.theme.style.fill = Some(fill);
+                },
+            }
+            if let Some(background) = theme.parse("background", parse_color)? {
+                self.theme.background = background;
+            }
+        }
+
+        if let Some(render) = root.section("render")? {
+            render.only(&["fps-cap"])?;
+            if let Some(fps) = render.number("fps-cap")? {
+                if fps <= 0.0 {
+                    return Err(render.invalid("fps-cap", "Must be positive"));
+                }
+                self.fps_cap = Some(fps);
+            }
+        }
+
+        if let Some(ipc) = root.section("ipc")? {
+            ipc.only(&["control", "socket", "dbus"])?;
+            if let Some(control) = ipc.boolean("control")? {
+                self.ipc.control = control;
+            }
+            if let Some(socket) = ipc.string("socket")? {
+                self.ipc.socket = Some(resolve(base, socket));
+            }
+            if let Some(dbus) = ipc.boolean("dbus")? {
+                self.ipc.dbus = dbus;
+            }
+        }
+
+        if let Some(session) = root.section("session")? {
+            session.only(&["supervise", "window"])?;
+            if let Some(supervise) = session.boolean("supervise")? {
+                self.supervise = supervise;
+            }
+            if let Some(window) = session.boolean("window")? {
+                self.window = window;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Consume a command line flag taking a value, returns false for flags that aren't settings.
+    pub fn apply_flag(&mut self, flag: &str, value: &str) -> Result<bool, String> {
+        if self.placement.apply_flag(flag, value)? {
+            return Ok(true);
+        }
+
+        match flag {
+            "--class" => self.class = value.to_string(),
+            "--expression" => self.expression = value.to_string(),
+            "--layout" => self.layout.mode = value.parse()?,
+            "--align" => self.layout.align = value.parse()?,
+            "--padding" => self.layout.padding = value.parse()?,
+            "--fps-cap" => self.fps_cap = match value.parse::<f64>() {
+                Ok(fps) if fps > 0.0 && fps.is_finite() => Some(fps),
+                _ => return Err(format!("Invalid frame rate {}", value)),
+            },
+            "--background" => self.theme.background = parse_color(value)?,
+            "--control-socket" => {
+                self.ipc.control = true;
+                self.ipc.socket = Some(PathBuf::from(value));
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // Consume a command line switch, returns false for switches that aren't settings.
+    pub fn apply_switch(&mut self, switch: &str) -> bool {
+        match switch {
+            "--supervise" => self.supervise = true,
+            "--window" => self.window = true,
+            "--no-blink" => self.blink.enabled = false,
+            "--no-control" => self.ipc.control = false,
+            "--no-dbus" => self.ipc.dbus = false,
+            _ => return false,
+        }
+        true
+    }
+}
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    Color::from_hex(value).ok_or_else(|| format!("Invalid color {}, expected RRGGBB or RRGGBBAA", value))
+}
+
+fn resolve(base: &Path, path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => base.join(path),
+    }
+}
+
+// A table of the config file, keeping track of where it is so errors can name the key.
+struct Section<'a> {
+    prefix: String,
+    table: &'a Table,
+}
+
+impl<'a> Section<'a> {
+    fn key(&self, name: &str) -> String {
+        match self.prefix.is_empty() {
+            true => name.to_string(),
+            false => format!("{}.{}", self.prefix, name),
+        }
+    }
+
+    fn invalid(&self, name: &str, message: &str) -> ConfigError {
+        ConfigError::Invalid { key: self.key(name), message: message.to_string() }
+    }
+
+    // Typos shouldn't be silently ignored
+    fn only(&self, known: &[&str]) -> Result<(), ConfigError> {
+        match self.table.keys().find(|key| !known.contains(&key.as_str())) {
+            Some(key) => Err(self.invalid(key, &format!("Unknown setting, expected one of {}", known.join(", ")))),
+            None => Ok(()),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&'a Value> {
+        self.table.get(name)
+    }
+
+    fn section(&self, name: &str) -> Result<Option<Section<'a>>, ConfigError> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(Value::Table(table)) => Ok(Some(Section { prefix: self.key(name), table })),
+            Some(_) => Err(self.invalid(name, "Expected a table")),
+        }
+    }
+
+    fn string(&self, name: &str) -> Result<Option<&'a str>, ConfigError> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value)),
+            Some(_) => Err(self.invalid(name, "Expected a string")),
+        }
+    }
+
+    fn boolean(&self, name: &str) -> Result<Option<bool>, ConfigError> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(Value::Boolean(value)) => Ok(Some(*value)),
+            Some(_) => Err(self.invalid(name, "Expected true or false")),
+        }
+    }
+
+    fn integer(&self, name: &str) -> Result<Option<i64>, ConfigError> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(Value::Integer(value)) => Ok(Some(*value)),
+            Some(_) => Err(self.invalid(name, "Expected an integer")),
+        }
+    }
+
+    fn number(&self, name: &str) -> Result<Option<f64>, ConfigError> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(Value::Integer(value)) => Ok(Some(*value as f64)),
+            Some(Value::Float(value)) if value.is_finite() => Ok(Some(*value)),
+            Some(_) => Err(self.invalid(name, "Expected a number")),
+        }
+    }
+
+    // Seconds as a number, or a string with a unit like "300ms"
+    fn duration(&self, name: &str) -> Result<Option<Duration>, ConfigError> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(Value::String(value)) => headless::parse_duration(value)
+                .map(Some)
+                .map_err(|_| self.invalid(name, &format!("Invalid duration {}", value))),
+            Some(_) => match self.number(name)?.and_then(headless::duration_from_secs) {
+                Some(duration) => Ok(Some(duration)),
+                None => Err(self.invalid(name, "Expected a positive duration of at most a day")),
+            },
+        }
+    }
+
+    // A string (or number, for settings like margin = 10) handed to the same parser as the command line flag.
+    fn parse<T>(&self, name: &str, parse: impl FnOnce(&str) -> Result<T, String>) -> Result<Option<T>, ConfigError> {
+        let value = match self.get(name) {
+            None => return Ok(None),
+            Some(Value::String(value)) => value.clone(),
+            Some(Value::Integer(value)) => value.to_string(),
+            Some(Value::Float(value)) => value.to_string(),
+            Some(_) => return Err(self.invalid(name, "Expected a string")),
+        };
+        parse(&value).map(Some).map_err(|message| self.invalid(name, &message))
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    fmt, io::{self, BufRead, BufReader, ErrorKind, Write}, os::unix::net::UnixStream, path::{Path, PathBuf}
+};
+
+use serde_json::Value;
+
+#[derive(Debug)]
+pub enum ClientError {
+    NoRuntimeDir,               // $XDG_RUNTIME_DIR isn't set and no socket was given
+    NotRunning(PathBuf),        // Nothing listens on the socket
+    Io(io::Error),
+    Protocol(String),           // The answer wasn't what the protocol says it should be
+    Command(String),            // hyogen-ui refused the command
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::NoRuntimeDir => write!(f, "XDG_RUNTIME_DIR is not set, pass the control socket with --socket"),
+            ClientError::NotRunning(path) => write!(f, "hyogen-ui is not running (nothing is listening on {})", path.display()),
+            ClientError::Io(err) => write!(f, "Lost the connection to hyogen-ui: {}", err),
+            ClientError::Protocol(err) => write!(f, "Unexpected answer from hyogen-ui: {}", err),
+            ClientError::Command(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(err: io::Error) -> Self {
+        ClientError::Io(err)
+    }
+}
+
+// Connection to a running face's control socket, one request at a time.
+pub struct ControlClient {
+    stream: UnixStream,
+    reader: BufReader<UnixStream>,
+}
+
+impl ControlClient {
+    pub fn connect(path: &Path) -> Result<Self, ClientError> {
+        let stream = match UnixStream::connect(path) {
+            Ok(stream) => stream,
+            // A missing socket or a stale one left behind both mean there's no face to talk to
+            Err(err) if matches!(err.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => {
+                return Err(ClientError::NotRunning(path.to_path_buf()));
+            }
+            Err(err) => return Err(err.into()),
+        };
+        Self::from_stream(stream)
+    }
+
+    // Use a connection made some other way, e.g. `ControlServer::connect_local`.
+    pub fn from_stream(stream: UnixStream) -> Result<Self, ClientError> {
+        let reader = BufReader::new(stream.try_clone()?);
+        Ok(Self { stream, reader })
+    }
+
+    // Connect to the default socket, see `default_socket_path`.
+    pub fn connect_default() -> Result<Self, ClientError> {
+        let path = super::default_socket_path().ok_or(ClientError::NoRuntimeDir)?;
+        Self::connect(&path)
+    }
+
+    // Send a command and wait for its answer, refused commands become `ClientError::Command`.
+    pub fn request(&mut self, request: &Value) -> Result<Value, ClientError> {
+        self.stream.write_all(format!("{}\n", request).as_bytes())?;
+
+        let response = self.next_line()?;
+        match response.get("ok").and_then(Value::as_bool) {
+            Some(true) => Ok(response),
+            Some(false) => {
+                let err = response.get("error").and_then(Value::as_str).unwrap_or("Command failed");
+                Err(ClientError::Command(err.to_string()))
+            }
+            None => Err(ClientError::Protocol(format!("missing \"ok\" in {}", response))),
+        }
+    }
+
+    // Next event after subscribing, None once hyogen-ui went away.
+    pub fn next_event(&mut self) -> Result<Option<Value>, ClientError> {
+        match self.next_line() {
+            Ok(event) => Ok(Some(event)),
+            Err(ClientError::Io(err)) if err.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn next_line(&mut self) -> Result<Value, ClientError> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "hyogen-ui closed the connection").into());
+        }
+        serde_json::from_str(&line).map_err(|err| ClientError::Protocol(err.to_string()))
+    }
+}
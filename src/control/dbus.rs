@@ -0,0 +1,247 @@
+use std::{
+    collections::HashMap, fmt, io, net::Shutdown, os::unix::net::UnixStream, sync::Mutex, thread
+};
+
+use calloop::LoopHandle;
+use serde_json::{Map, Value};
+use zbus::{blocking, fdo, interface, SignalContext};
+
+use super::{client::{ClientError, ControlClient}, ControlHandler, ControlServer};
+
+pub const BUS_NAME: &str = "org.osmos.Hyogen";
+pub const OBJECT_PATH: &str = "/org/osmos/Hyogen";
+
+#[derive(Debug)]
+pub enum DbusError {
+    Io(io::Error),
+    Bus(zbus::Error),
+}
+
+impl fmt::Display for DbusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbusError::Io(err) => write!(f, "Failed to connect to the face: {}", err),
+            DbusError::Bus(err) => write!(f, "D-Bus: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for DbusError {
+    fn from(err: io::Error) -> Self {
+        DbusError::Io(err)
+    }
+}
+
+impl From<zbus::Error> for DbusError {
+    fn from(err: zbus::Error) -> Self {
+        DbusError::Bus(err)
+    }
+}
+
+impl From<ClientError> for DbusError {
+    fn from(err: ClientError) -> Self {
+        match err {
+            ClientError::Io(err) => DbusError::Io(err),
+            err => DbusError::Io(io::Error::other(err.to_string())),
+        }
+    }
+}
+
+// The org.osmos.Hyogen interface. It speaks the control protocol to the face over an in-process connection,
+// so D-Bus calls behave exactly like commands on the control socket.
+struct HyogenInterface {
+    client: Mutex<ControlClient>,
+}
+
+impl HyogenInterface {
+    fn request(&self, request: Map<String, Value>) -> fdo::Result<Value> {
+        let mut client = self.client.lock().map_err(|_| fdo::Error::Failed("Connection to the face is broken".to_string()))?;
+        client.request(&Value::Object(request)).map_err(|err| match err {
+            ClientError::Command(err) => fdo::Error::InvalidArgs(err),
+            err => fdo::Error::Failed(err.to_string()),
+        })
+    }
+
+    fn command(&self, command: &str) -> fdo::Result<()> {
+        self.request(request(command)).map(|_| ())
+    }
+
+    // Current state, see the `state` command. Properties read as defaults if the face is gone.
+    fn state(&self) -> Value {
+        self.request(request("state")).map(|response| response["state"].clone()).unwrap_or(Value::Null)
+    }
+}
+
+#[interface(name = "org.osmos.Hyogen")]
+impl HyogenInterface {
+    // Morph to an expression, `duration` in seconds and an empty `easing` for linear.
+    fn set_expression(&self, expression: &str, duration: f64, easing: &str) -> fdo::Result<()> {
+        let mut request = request("set");
+        insert_transition(&mut request, expression, duration, easing);
+        self.request(request).map(|_| ())
+    }
+
+    // Morph to a weighted mix of expressions.
+    fn blend(&self, weights: HashMap<String, f64>, duration: f64, easing: &str) -> fdo::Result<()> {
+        let mut request = request("blend");
+        let weights = weights.into_iter().map(|(expression, weight)| (expression, Value::from(weight))).collect();
+        request.insert("weights".to_string(), Value::Object(weights));
+        insert_transition(&mut request, "", duration, easing);
+        request.remove("expression");
+        self.request(request).map(|_| ())
+    }
+
+    // Play (expression, duration, easing) steps after whatever is playing, or right away with `replace`.
+    fn play_sequence(&self, steps: Vec<(String, f64, String)>, replace: bool) -> fdo::Result<()> {
+        let steps = steps.iter().map(|(expression, duration, easing)| {
+            let mut step = Map::new();
+            insert_transition(&mut step, expression, *duration, easing);
+            Value::Object(step)
+        }).collect();
+
+        let mut request = request("sequence");
+        request.insert("steps".to_string(), Value::Array(steps));
+        request.insert("replace".to_string(), Value::Bool(replace));
+        self.request(request).map(|_| ())
+    }
+
+    fn blink(&self) -> fdo::Result<()> {
+        self.command("blink")
+    }
+
+    fn pause(&self) -> fdo::Result<()> {
+        self.command("pause")
+    }
+
+    fn resume(&self) -> fdo::Result<()> {
+        self.command("resume")
+    }
+
+    fn list_expressions(&self) -> fdo::Result<Vec<String>> {
+        let response = self.request(request("list"))?;
+        Ok(response["expressions"].as_array().into_iter().flatten().filter_map(Value::as_str).map(str::to_string).collect())
+    }
+
+    #[zbus(property)]
+    fn expression(&self) -> String {
+        self.state()["expression"].as_str().unwrap_or_default().to_string()
+    }
+
+    #[zbus(property)]
+    fn animating(&self) -> bool {
+        self.state()["animating"].as_bool().unwrap_or(false)
+    }
+
+    #[zbus(property)]
+    fn paused(&self) -> bool {
+        self.state()["paused"].as_bool().unwrap_or(false)
+    }
+
+    #[zbus(signal)]
+    async fn transition_started(ctxt: &SignalContext<'_>, expression: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn transition_finished(ctxt: &SignalContext<'_>, expression: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn region_tapped(ctxt: &SignalContext<'_>, region: u32) -> zbus::Result<()>;
+}
+
+fn request(command: &str) -> Map<String, Value> {
+    let mut request = Map::new();
+    request.insert("cmd".to_string(), Value::String(command.to_string()));
+    request
+}
+
+fn insert_transition(request: &mut Map<String, Value>, expression: &str, duration: f64, easing: &str) {
+    request.insert("expression".to_string(), Value::String(expression.to_string()));
+    request.insert("duration".to_string(), Value::from(duration));
+    if !easing.is_empty() {
+        request.insert("easing".to_string(), Value::String(easing.to_string()));
+    }
+}
+
+// Owns the org.osmos.Hyogen name while alive. Face events are turned into signals on a separate thread.
+pub struct DbusService {
+    _connection: blocking::Connection,
+    events: UnixStream,     // Shut down to stop the signal thread
+}
+
+impl DbusService {
+    // Serve on the session bus, or on the bus at `address`.
+    pub fn start<D: ControlHandler + 'static>(address: Option<&str>, handle: &LoopHandle<'static, D>) -> Result<Self, DbusError> {
+        let client = ControlClient::from_stream(ControlServer::connect_local(handle)?)?;
+        let events = ControlServer::connect_local(handle)?;
+
+        let builder = match address {
+            Some(address) => blocking::connection::Builder::address(address)?,
+            None => blocking::connection::Builder::session()?,
+        };
+        let connection = builder
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, HyogenInterface { client: Mutex::new(client) })?
+            .build()?;
+
+        let subscription = ControlClient::from_stream(events.try_clone()?)?;
+        let signal_connection = connection.clone();
+        thread::Builder::new()
+            .name("hyogen-dbus".to_string())
+            .spawn(move || forward_events(subscription, signal_connection))?;
+
+        tracing::info!("Serving {} on D-Bus", BUS_NAME);
+        Ok(Self { _connection: connection, events })
+    }
+}
+
+impl Drop for DbusService {
+    fn drop(&mut self) {
+        let _ = self.events.shutdown(Shutdown::Both);
+    }
+}
+
+// Emit a signal for every face event until the face goes away.
+fn forward_events(mut subscription: ControlClient, connection: blocking::Connection) {
+    // Answered once the face's event loop runs, which is why this isn't done in `start`
+    // Only what maps to signals and properties
+    let mut subscribe = request("subscribe");
+    let events = ["transition_started", "transition_finished", "tapped", "paused", "resumed"];
+    subscribe.insert("events".to_string(), Value::Array(events.into_iter().map(Value::from).collect()));
+    if let Err(err) = subscription.request(&Value::Object(subscribe)) {
+        tracing::error!("Failed to subscribe to face events: {}", err);
+        return;
+    }
+
+    let interface = match connection.object_server().interface::<_, HyogenInterface>(OBJECT_PATH) {
+        Ok(interface) => interface,
+        Err(err) => {
+            tracing::error!("D-Bus interface is gone: {}", err);
+            return;
+        }
+    };
+
+    while let Ok(Some(event)) = subscription.next_event() {
+        let ctxt = interface.signal_context();
+        let iface = interface.get();
+        let result = zbus::block_on(async {
+            match event["event"].as_str() {
+                Some("transition_started") => {
+                    HyogenInterface::transition_started(ctxt, event["expression"].as_str().unwrap_or_default()).await?;
+                    iface.expression_changed(ctxt).await?;
+                    iface.animating_changed(ctxt).await
+                }
+                Some("transition_finished") => {
+                    HyogenInterface::transition_finished(ctxt, event["expression"].as_str().unwrap_or_default()).await?;
+                    iface.animating_changed(ctxt).await
+                }
+                Some("tapped") => HyogenInterface::region_tapped(ctxt, event["region"].as_u64().unwrap_or_default() as u32).await,
+                Some("paused") | Some("resumed") => iface.paused_changed(ctxt).await,
+                _ => Ok(()),
+            }
+        });
+
+        if let Err(err) = result {
+            tracing::warn!("Failed to emit a D-Bus signal: {}", err);
+        }
+    }
+    tracing::info!("Stopped emitting D-Bus signals, the face closed the event stream");
+}
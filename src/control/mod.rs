@@ -0,0 +1,61 @@
+pub mod protocol;
+#[cfg(feature = "ipc")]
+pub mod client;
+#[cfg(feature = "ipc")]
+pub mod dbus;
+#[cfg(feature = "ipc")]
+mod server;
+#[cfg(feature = "wayland")]
+pub mod stdin;
+
+#[cfg(feature = "ipc")]
+pub use server::ControlServer;
+
+use std::{env, io::{self, Write}, path::PathBuf};
+
+use serde_json::Value;
+
+use protocol::{Command, Request};
+
+use crate::events::{Event, EventBus, EventFilter};
+
+// Whatever the control socket drives, usually the face.
+pub trait ControlHandler {
+    fn handle_command(&mut self, command: Command) -> Result<Value, String>;
+    fn events(&mut self) -> &mut EventBus;
+}
+
+// Event handler writing one JSON line per event to a blocking stream, like stdout with --stdin.
+pub fn json_lines(mut stream: impl Write + 'static) -> impl FnMut(&Event) -> bool {
+    // Subscribers that went away are dropped
+    move |event| {
+        let line = format!("{}\n", event.to_json());
+        stream.write_all(line.as_bytes()).and_then(|_| stream.flush()).is_ok()
+    }
+}
+
+// `$XDG_RUNTIME_DIR/hyogen-ui.sock`, None without a runtime directory.
+pub fn default_socket_path() -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| PathBuf::from(dir).join("hyogen-ui.sock"))
+}
+
+// Answer one request line. Subscribing is up to the caller, as only it knows where events should go.
+pub fn respond<D: ControlHandler>(line: &str, state: &mut D, subscribe: impl FnOnce(&mut EventBus, EventFilter) -> io::Result<()>) -> Value {
+    let request = match Request::parse(line) {
+        Ok(request) => request,
+        Err(err) => return protocol::error(protocol::recover_id(line), &err),
+    };
+
+    match request.command {
+        Command::Subscribe(filter) => match subscribe(state.events(), filter) {
+            Ok(()) => protocol::ok(request.id, Value::Null),
+            Err(err) => protocol::error(request.id, &err.to_string()),
+        },
+        command => match state.handle_command(command) {
+            Ok(body) => protocol::ok(request.id, body),
+            Err(err) => protocol::error(request.id, &err),
+        },
+    }
+}
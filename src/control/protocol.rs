@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+
+use crate::{animator::easing::Easing, events::EventFilter, headless};
+
+// A morph towards a named expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub expression: String,
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Set(Transition),
+    Sequence { steps: Vec<Transition>, replace: bool },     // Appended after what's playing unless `replace`
+    Blend { weights: Vec<(String, f64)>, duration: Duration, easing: Easing },
+    Blink,
+    Gaze { x: f64, y: f64, duration: Duration, easing: Easing },   // -1..1 on both axes, 0 looks straight ahead
+    Pause,
+    Resume,
+    State,
+    List,
+    Subscribe(EventFilter),
+}
+
+// One line sent by a client, `id` is echoed back in the response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub id: Option<Value>,
+    pub command: Command,
+}
+
+// How long a transition takes if the client doesn't say.
+pub const DEFAULT_DURATION: Duration = Duration::from_millis(300);
+
+impl Request {
+    // Parse a JSON line like {"cmd": "set", "expression": "happy", "duration": "300ms", "easing": "out-cubic"}.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(line).map_err(|err| format!("Invalid JSON: {}", err))?;
+        let object = value.as_object().ok_or("Expected a JSON object")?;
+        let id = object.get("id").cloned();
+
+        let command = match object.get("cmd").and_then(Value::as_str).ok_or("Missing \"cmd\"")? {
+            "set" => Command::Set(parse_transition(object)?),
+            "sequence" => {
+                let steps = object.get("steps").and_then(Value::as_array).ok_or("\"steps\" must be an array")?
+                    .iter()
+                    .map(|step| step.as_object().ok_or_else(|| "Every step must be an object".to_string()).and_then(parse_transition))
+                    .collect::<Result<Vec<Transition>, String>>()?;
+                if steps.is_empty() {
+                    return Err("\"steps\" is empty".to_string());
+                }
+                Command::Sequence { steps, replace: object.get("replace").and_then(Value::as_bool).unwrap_or(false) }
+            }
+            "blend" => {
+                let weights = object.get("weights").and_then(Value::as_object).ok_or("\"weights\" must be an object")?
+                    .iter()
+                    .map(|(expression, weight)| match weight.as_f64() {
+                        Some(weight) if weight >= 0.0 && weight.is_finite() => Ok((expression.clone(), weight)),
+                        _ => Err(format!("Invalid weight for {}", expression)),
+                    })
+                    .collect::<Result<Vec<(String, f64)>, String>>()?;
+                if weights.iter().all(|(_, weight)| *weight == 0.0) {
+                    return Err("\"weights\" needs at least one positive weight".to_string());
+                }
+                Command::Blend { weights, duration: parse_duration(object)?, easing: parse_easing(object)? }
+            }
+            "blink" => Command::Blink,
+            "gaze" => {
+                let coordinate = |name: &str| match object.get(name).and_then(Value::as_f64) {
+                    Some(value) if value.is_finite() => Ok(value),
+                    _ => Err(format!("\"{}\" must be a number", name)),
+                };
+                Command::Gaze { x: coordinate("x")?, y: coordinate("y")?, duration: parse_duration(object)?, easing: parse_easing(object)? }
+            }
+            "pause" => Command::Pause,
+            "resume" => Command::Resume,
+            "state" => Command::State,
+            "list" => Command::List,
+            "subscribe" => match object.get("events") {
+                // Event names or categories, everything by default
+                None => Command::Subscribe(EventFilter::All),
+                Some(events) => {
+                    let names = events.as_array().ok_or("\"events\" must be an array")?
+                        .iter()
+                        .map(|name| name.as_str().ok_or_else(|| "Every event must be a string".to_string()))
+                        .collect::<Result<Vec<&str>, String>>()?;
+                    Command::Subscribe(EventFilter::parse(&names)?)
+                }
+            },
+            other => return Err(format!("Unknown command {}", other)),
+        };
+
+        Ok(Self { id, command })
+    }
+}
+
+fn parse_transition(object: &Map<String, Value>) -> Result<Transition, String> {
+    let expression = object.get("expression").and_then(Value::as_str).ok_or("Missing \"expression\"")?;
+    Ok(Transition {
+        expression: expression.to_string(),
+        duration: parse_duration(object)?,
+        easing: parse_easing(object)?,
+    })
+}
+
+// The id of a line `Request::parse` rejected, so the error still reaches the right caller. Best effort for broken
+// JSON, it looks for "id": followed by a number or a string.
+pub fn recover_id(line: &str) -> Option<Value> {
+    if let Ok(Value::Object(object)) = serde_json::from_str::<Value>(line) {
+        return object.get("id").cloned();
+    }
+
+    line.match_indices("\"id\"").find_map(|(at, key)| {
+        let rest = line[at + key.len()..].trim_start().strip_prefix(':')?.trim_start();
+        let end = match rest.strip_prefix('"') {
+            // Up to the closing quote, skipping escaped ones
+            Some(string) => {
+                let mut escaped = false;
+                let (end, _) = string.char_indices().find(|&(_, c)| {
+                    let closing = c == '"' && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    closing
+                })?;
+                end + 2
+            }
+            None => rest.find(|c: char| c == ',' || c == '}' || c.is_whitespace()).unwrap_or(rest.len()),
+        };
+        match serde_json::from_str(&rest[..end]).ok()? {
+            id @ (Value::Number(_) | Value::String(_)) => Some(id),
+            _ => None,
+        }
+    })
+}
+
+pub fn parse_duration(object: &Map<String, Value>) -> Result<Duration, String> {
+    // Seconds as a number, or a string with a unit like "300ms"
+    match object.get("duration") {
+        None => Ok(DEFAULT_DURATION),
+        Some(Value::Number(seconds)) => seconds.as_f64()
+            .and_then(headless::duration_from_secs)
+            .ok_or_else(|| format!("Invalid duration {}", seconds)),
+        Some(Value::String(duration)) => headless::parse_duration(duration).map_err(|_| format!("Invalid duration {}", duration)),
+        Some(_) => Err("\"duration\" must be a number or a string".to_string()),
+    }
+}
+
+fn parse_easing(object: &Map<String, Value>) -> Result<Easing, String> {
+    match object.get("easing") {
+        None => Ok(Easing::default()),
+        Some(easing) => easing.as_str().ok_or("\"easing\" must be a string")?.parse(),
+    }
+}
+
+// Successful response, `body` (an object) is merged in.
+pub fn ok(id: Option<Value>, body: Value) -> Value {
+    let mut response = match body {
+        Value::Object(object) => object,
+        _ => Map::new(),
+    };
+    response.insert("ok".to_string(), Value::Bool(true));
+    if let Some(id) = id {
+        response.insert("id".to_string(), id);
+    }
+    Value::Object(response)
+}
+
+pub fn error(id: Option<Value>, message: &str) -> Value {
+    let mut response = Map::new();
+    response.insert("ok".to_string(), Value::Bool(false));
+    response.insert("error".to_string(), Value::String(message.to_string()));
+    if let Some(id) = id {
+        response.insert("id".to_string(), id);
+    }
+    Value::Object(response)
+}
@@ -0,0 +1,232 @@
+use std::{
+    cell::RefCell,
+    fs,
+    io::{self, ErrorKind, Read, Write},
+    net::Shutdown,
+    os::unix::{fs::{DirBuilderExt, PermissionsExt}, net::{UnixListener, UnixStream}},
+    path::{Path, PathBuf},
+    process,
+    rc::Rc,
+};
+
+use calloop::{generic::Generic, Interest, LoopHandle, Mode, PostAction};
+
+use super::{respond, ControlHandler};
+
+// Longest line a client may send before it gets disconnected.
+const MAX_LINE: usize = 64 * 1024;
+// Most output a client may leave unread before it gets disconnected.
+const MAX_PENDING: usize = 1024 * 1024;
+
+// Listens for newline delimited JSON commands, the socket file is removed when dropped.
+pub struct ControlServer {
+    path: PathBuf,
+}
+
+impl ControlServer {
+    pub fn bind<D: ControlHandler + 'static>(path: &Path, handle: &LoopHandle<'static, D>) -> io::Result<Self> {
+        // A leftover socket from a crashed instance is replaced, a live one is left alone
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(ErrorKind::AddrInUse, format!("{} is already in use", path.display())));
+            }
+            fs::remove_file(path)?;
+        }
+
+        let listener = Self::bind_private(path)?;
+        listener.set_nonblocking(true)?;
+
+        let client_handle = handle.clone();
+        handle.insert_source(Generic::new(listener, Interest::READ, Mode::Level), move |_, listener, _| {
+            loop {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if let Err(err) = Self::insert_client(stream, &client_handle) {
+                            tracing::warn!("Failed to set up a control connection: {}", err);
+                        }
+                    }
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                    Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                    Err(err) => {
+                        tracing::warn!("Failed to accept a control connection: {}", err);
+                        break;
+                    }
+                }
+            }
+            Ok(PostAction::Continue)
+        }).map_err(|err| io::Error::other(err.error))?;
+
+        tracing::info!("Listening for commands on {}", path.display());
+        Ok(Self { path: path.to_path_buf() })
+    }
+
+    // Bind in a directory only we can enter and move the socket into place once it's 0600, so nobody else
+    // can connect in between.
+    fn bind_private(path: &Path) -> io::Result<UnixListener> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let private = path.with_file_name(format!(".{}.{}", name, process::id()));
+        fs::DirBuilder::new().mode(0o700).create(&private)?;
+
+        let staged = private.join("socket");
+        let listener = UnixListener::bind(&staged).and_then(|listener| {
+            fs::set_permissions(&staged, fs::Permissions::from_mode(0o600))?;
+            fs::rename(&staged, path)?;
+            Ok(listener)
+        });
+        let _ = fs::remove_file(&staged);
+        let _ = fs::remove_dir(&private);
+        listener
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // A connection from within the process, for bridges like D-Bus that speak the same protocol.
+    pub fn connect_local<D: ControlHandler + 'static>(handle: &LoopHandle<'static, D>) -> io::Result<UnixStream> {
+        let (ours, theirs) = UnixStream::pair()?;
+        Self::insert_client(ours, handle)?;
+        Ok(theirs)
+    }
+
+    fn insert_client<D: ControlHandler + 'static>(stream: UnixStream, handle: &LoopHandle<'static, D>) -> io::Result<()> {
+        stream.set_nonblocking(true)?;
+        let outbox = Outbox::new(stream.try_clone()?, handle.clone());
+
+        let mut buffer = Vec::new();
+        let source = Generic::new(stream, Interest::READ, Mode::Level);
+        let result = handle.insert_source(source, move |_, stream, state| {
+            let stream: &UnixStream = stream;
+
+            // Read everything available, then answer every complete line
+            let mut closed = false;
+            let mut chunk = [0u8; 4096];
+            loop {
+                match (&*stream).read(&mut chunk) {
+                    Ok(0) => {
+                        closed = true;
+                        break;
+                    }
+                    Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                    Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                    Err(_) => return Ok(PostAction::Remove),
+                }
+            }
+
+            while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let response = respond(line.trim(), state, |events, filter| {
+                    // Events are queued behind the responses on the same connection
+                    let outbox = outbox.clone();
+                    events.subscribe(filter, move |event| Outbox::send(&outbox, &event.to_json().to_string()));
+                    Ok(())
+                });
+                if !Outbox::send(&outbox, &response.to_string()) {
+                    return Ok(PostAction::Remove);
+                }
+            }
+
+            if closed || buffer.len() > MAX_LINE || outbox.borrow().closed {
+                return Ok(PostAction::Remove);
+            }
+            Ok(PostAction::Continue)
+        });
+
+        result.map(|_| ()).map_err(|err| io::Error::other(err.error))
+    }
+}
+
+// What's written to a connection. Whatever the socket doesn't take right away waits here and is written once
+// it's writable again, so lines are never cut short. A client that stops reading is shut down instead, it
+// sees the end of the stream rather than missing lines.
+struct Outbox<D: 'static> {
+    stream: UnixStream,
+    pending: Vec<u8>,
+    handle: LoopHandle<'static, D>,
+    flushing: bool,         // Waiting for the socket to become writable
+    closed: bool,
+}
+
+impl<D: 'static> Outbox<D> {
+    fn new(stream: UnixStream, handle: LoopHandle<'static, D>) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Self { stream, pending: Vec::new(), handle, flushing: false, closed: false }))
+    }
+
+    // Queue a line, returns false once the connection is closed.
+    fn send(outbox: &Rc<RefCell<Self>>, line: &str) -> bool {
+        let mut this = outbox.borrow_mut();
+        if this.closed {
+            return false;
+        }
+        this.pending.extend_from_slice(line.as_bytes());
+        this.pending.push(b'\n');
+
+        if this.flush().is_err() || this.pending.len() > MAX_PENDING {
+            this.close();
+            return false;
+        }
+        if !this.pending.is_empty() && !this.flushing {
+            this.flushing = Self::flush_when_writable(outbox, &this);
+            if !this.flushing {
+                this.close();
+                return false;
+            }
+        }
+        true
+    }
+
+    fn flush_when_writable(outbox: &Rc<RefCell<Self>>, this: &Self) -> bool {
+        let Ok(stream) = this.stream.try_clone() else {
+            return false;
+        };
+        let outbox = outbox.clone();
+        let source = Generic::new(stream, Interest::WRITE, Mode::Level);
+        this.handle.insert_source(source, move |_, _, _| {
+            let mut this = outbox.borrow_mut();
+            if this.flush().is_err() {
+                this.close();
+            }
+            match this.pending.is_empty() {
+                true => {
+                    this.flushing = false;
+                    Ok(PostAction::Remove)
+                }
+                false => Ok(PostAction::Continue),
+            }
+        }).is_ok()
+    }
+
+    // Write as much as the socket takes without blocking.
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.pending.is_empty() {
+            match self.stream.write(&self.pending) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.pending.drain(..written);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+        self.pending.clear();
+        self.closed = true;
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    collections::VecDeque, fmt, io::{self, BufRead, BufReader, Read, Write}, sync::{Arc, Mutex}, thread, time::Instant
+};
+
+use calloop::{ping::{make_ping, Ping}, timer::{TimeoutAction, Timer}, LoopHandle};
+use serde_json::Value;
+
+use super::{protocol, respond, ControlHandler};
+
+// Commands read line by line from stdin (`--stdin`), answers are printed to stdout. Besides the control
+// commands, {"cmd": "wait", "duration": "1s"} holds off the lines after it.
+//
+// Stdin is read on a thread of its own from the first session on, and attached to the event loop of every session.
+#[derive(Clone)]
+pub struct StdinScript {
+    shared: Arc<Mutex<Script>>,
+}
+
+struct Script {
+    lines: VecDeque<String>,                // Read but not run yet
+    waiting_until: Option<Instant>,
+    wake: Option<Ping>,                     // Wakes the event loop of the current session
+    input: Option<Box<dyn Read + Send>>,    // Stdin, until the first session starts reading it
+    output: Box<dyn Write + Send>,          // Where answers go, stdout
+}
+
+impl fmt::Debug for StdinScript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StdinScript").finish_non_exhaustive()
+    }
+}
+
+impl StdinScript {
+    pub fn start() -> Self {
+        Self::with_io(io::stdin(), io::stdout())
+    }
+
+    // Read commands from `input` instead, answering on `output`.
+    pub fn with_io(input: impl Read + Send + 'static, output: impl Write + Send + 'static) -> Self {
+        let script = Script {
+            lines: VecDeque::new(),
+            waiting_until: None,
+            wake: None,
+            input: Some(Box::new(input)),
+            output: Box::new(output),
+        };
+        Self { shared: Arc::new(Mutex::new(script)) }
+    }
+
+    // Queue the lines of `input` as they come in, on a thread of its own.
+    fn read(&self, input: Box<dyn Read + Send>) -> io::Result<()> {
+        let reader = self.shared.clone();
+        thread::Builder::new().name("hyogen-stdin".to_string()).spawn(move || {
+            for line in BufReader::new(input).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let Ok(mut script) = reader.lock() else {
+                    break;
+                };
+                script.lines.push_back(line);
+                if let Some(wake) = &script.wake {
+                    wake.ping();
+                }
+            }
+            tracing::debug!("Reached the end of the commands");
+        })?;
+        Ok(())
+    }
+
+    // Run lines on this event loop from now on, including any read while there was none.
+    pub fn attach<D: ControlHandler + 'static>(&self, handle: &LoopHandle<'static, D>) -> io::Result<()> {
+        let (wake, source) = make_ping()?;
+
+        let (script, script_handle) = (self.clone(), handle.clone());
+        handle.insert_source(source, move |_, _, state| script.run(state, &script_handle))
+            .map_err(|err| io::Error::other(err.error))?;
+
+        let input = match self.shared.lock() {
+            Ok(mut script) => {
+                // A wait that was cut short by a reconnect is over
+                script.waiting_until = None;
+                wake.ping();
+                script.wake = Some(wake);
+                script.input.take()
+            }
+            Err(_) => None,
+        };
+
+        // Reading starts with the first session, the thread then inherits the signal mask of its event loop. Spawned
+        // any earlier, SIGINT and friends could be delivered to it instead of the loop's signalfd.
+        match input {
+            Some(input) => self.read(input),
+            None => Ok(()),
+        }
+    }
+
+    fn run<D: ControlHandler + 'static>(&self, state: &mut D, handle: &LoopHandle<'static, D>) {
+        loop {
+            let line = {
+                let Ok(mut script) = self.shared.lock() else {
+                    return;
+                };
+                if script.waiting_until.is_some_and(|until| Instant::now() < until) {
+                    return;
+                }
+                script.waiting_until = None;
+
+                match script.lines.pop_front() {
+                    Some(line) => line,
+                    None => return,
+                }
+            };
+
+            let response = match wait(&line) {
+                Some(Ok((id, duration))) => {
+                    if let Ok(mut script) = self.shared.lock() {
+                        script.waiting_until = Some(Instant::now() + duration);
+                    }
+
+                    // Pick up the remaining lines once the wait is over
+                    let (script, timer_handle) = (self.clone(), handle.clone());
+                    let timer = handle.insert_source(Timer::from_duration(duration), move |_, _, state| {
+                        script.run(state, &timer_handle);
+                        TimeoutAction::Drop
+                    });
+                    match timer {
+                        Ok(_) => {
+                            self.print(&protocol::ok(id, Value::Null));
+                            return;
+                        }
+                        Err(err) => protocol::error(id, &err.error.to_string()),
+                    }
+                }
+                Some(Err(response)) => response,
+                // Events already go to stdout, main subscribes it for the whole run
+                None => respond(&line, state, |_, _| Ok(())),
+            };
+            self.print(&response);
+        }
+    }
+
+    fn print(&self, response: &Value) {
+        if let Ok(mut script) = self.shared.lock() {
+            let _ = writeln!(script.output, "{}", response);
+            let _ = script.output.flush();
+        }
+    }
+}
+
+// Parse a `wait` line, None for any other command.
+fn wait(line: &str) -> Option<Result<(Option<Value>, std::time::Duration), Value>> {
+    let value: Value = serde_json::from_str(line).ok()?;
+    let object = value.as_object()?;
+    if object.get("cmd").and_then(Value::as_str) != Some("wait") {
+        return None;
+    }
+
+    let id = object.get("id").cloned();
+    if object.get("duration").is_none() {
+        return Some(Err(protocol::error(id, "\"wait\" needs a duration")));
+    }
+    Some(protocol::parse_duration(object).map(|duration| (id.clone(), duration)).map_err(|err| protocol::error(id, &err)))
+}
@@ -0,0 +1,144 @@
+use std::{path::Path, time::Instant};
+
+use calloop::{signals::{Signal, Signals}, timer::{TimeoutAction, Timer}, EventLoop, LoopHandle, RegistrationToken};
+use calloop_wayland_source::WaylandSource;
+use wayland_client::{Connection, EventQueue, QueueHandle};
+
+#[cfg(feature = "ipc")]
+use crate::control::{dbus::DbusService, ControlServer};
+use crate::{control::stdin::StdinScript, layer::HyogenLayer, watch};
+
+// Drives the face: Wayland events, animation timers and signals, plus any source registered through `handle`.
+pub struct HyogenLoop {
+    event_loop: EventLoop<'static, HyogenLayer>,
+    connection: Connection,
+    qh: QueueHandle<HyogenLayer>,
+    timer: Option<(Instant, RegistrationToken)>,   // Armed for the layer's next wakeup
+    #[cfg(feature = "ipc")]
+    control: Option<ControlServer>,                 // Lives as long as the loop it's registered with
+    #[cfg(feature = "ipc")]
+    dbus: Option<DbusService>,                      // Same
+}
+
+impl HyogenLoop {
+    pub fn new(connection: Connection, event_queue: EventQueue<HyogenLayer>) -> calloop::Result<Self> {
+        let event_loop = EventLoop::try_new()?;
+        let qh = event_queue.handle();
+
+        WaylandSource::new(connection.clone(), event_queue)
+            .insert(event_loop.handle())
+            .map_err(|err| err.error)?;
+
+        // Signals arrive through a signalfd, so they can't interrupt us in the middle of a commit
+        let signals = Signals::new(&[Signal::SIGINT, Signal::SIGTERM, Signal::SIGHUP])?;
+        let signal_qh = qh.clone();
+        event_loop.handle().insert_source(signals, move |event, _, state| match event.signal() {
+            Signal::SIGHUP => {
+                tracing::info!("Received SIGHUP, reloading");
+                state.reload(&signal_qh);
+            }
+            signal => {
+                tracing::info!("Received {}, saying goodbye", signal);
+                state.begin_exit(&signal_qh);
+            }
+        }).map_err(|err| err.error)?;
+
+        Ok(Self {
+            event_loop,
+            connection,
+            qh,
+            timer: None,
+            #[cfg(feature = "ipc")]
+            control: None,
+            #[cfg(feature = "ipc")]
+            dbus: None,
+        })
+    }
+
+    // Extension point for other event sources (sockets, file watchers...), their callbacks get the face state.
+    pub fn handle(&self) -> LoopHandle<'static, HyogenLayer> {
+        self.event_loop.handle()
+    }
+
+    // Accept control commands on a Unix socket, see `control`. The face works fine without one.
+    #[cfg(feature = "ipc")]
+    pub fn serve_control(&mut self, path: &Path) {
+        match ControlServer::bind(path, &self.event_loop.handle()) {
+            Ok(server) => self.control = Some(server),
+            Err(err) => tracing::warn!("Control socket is not available: {}", err),
+        }
+    }
+
+    // Offer org.osmos.Hyogen on the session bus, the face works fine without it too.
+    #[cfg(feature = "ipc")]
+    pub fn serve_dbus(&mut self) {
+        match DbusService::start(None, &self.event_loop.handle()) {
+            Ok(service) => self.dbus = Some(service),
+            Err(err) => tracing::warn!("D-Bus service is not available: {}", err),
+        }
+    }
+
+    // Run the commands piped in with --stdin on this loop.
+    pub fn serve_stdin(&mut self, script: &StdinScript) {
+        if let Err(err) = script.attach(&self.event_loop.handle()) {
+            tracing::warn!("Can't take commands from stdin: {}", err);
+        }
+    }
+
+    // Reload when a file changes, like on SIGHUP. Used for the config file and the HVF files, so the face
+    // recovers once a broken or missing one is fixed.
+    pub fn reload_on_change(&mut self, path: &Path) {
+        let (qh, changed) = (self.qh.clone(), path.to_path_buf());
+        let result = watch::watch_file(path, &self.event_loop.handle(), move |state: &mut HyogenLayer| {
+            tracing::info!("{} changed, reloading", changed.display());
+            state.reload(&qh);
+        });
+        if let Err(err) = result {
+            tracing::warn!("Not watching {} for changes: {}", path.display(), err);
+        }
+    }
+
+    pub fn queue_handle(&self) -> &QueueHandle<HyogenLayer> {
+        &self.qh
+    }
+
+    // Dispatch events until the face exits.
+    pub fn run(&mut self, state: &mut HyogenLayer) -> calloop::Result<()> {
+        while !state.exit() {
+            self.arm_timer(state);
+            self.event_loop.dispatch(None, state)?;
+        }
+
+        // Make sure the last frame reaches the compositor before the connection goes away
+        if let Err(err) = self.connection.flush() {
+            tracing::warn!("Failed to flush the last requests: {}", err);
+        }
+        Ok(())
+    }
+
+    // Keep a single timer pointed at the next time the layer has work to do.
+    fn arm_timer(&mut self, state: &HyogenLayer) {
+        let next = state.next_wakeup();
+        if self.timer.map(|(at, _)| at) == next {
+            return;
+        }
+
+        // Tokens of timers that already fired are stale, removing them is a no-op
+        if let Some((_, token)) = self.timer.take() {
+            self.event_loop.handle().remove(token);
+        }
+
+        if let Some(at) = next {
+            let qh = self.qh.clone();
+            let timer = self.event_loop.handle().insert_source(Timer::from_deadline(at), move |_, _, state| {
+                state.tick(&qh);
+                TimeoutAction::Drop
+            });
+
+            match timer {
+                Ok(token) => self.timer = Some((at, token)),
+                Err(err) => tracing::error!("Failed to arm the animation timer: {}", err.error),
+            }
+        }
+    }
+}
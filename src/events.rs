@@ -0,0 +1,239 @@
+use serde_json::{Map, Value};
+
+// Something that happened to the face. The animator, input handlers and the layer post these to the `EventBus`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    // Animation
+    TransitionStarted { expression: String },
+    TransitionFinished { expression: String },
+    Settled,                                            // Nothing is animating anymore, blinks included
+    Blink,
+    Gaze { x: f64, y: f64 },                            // New gaze target
+    Paused,
+    Resumed,
+
+    // Input, `surface` is the index of the face's surface
+    PointerEntered { surface: usize },
+    PointerLeft { surface: usize },
+    Tapped { region: usize },
+
+    // Lifecycle
+    Connected,                                          // To the compositor, again after a reconnect
+    Disconnected,
+    OutputAdded { name: String },
+    OutputRemoved { name: String },
+    Configured { surface: usize, width: u32, height: u32 },
+    Reloaded { restart: Vec<String> },                  // Changed settings that only apply after a restart
+    Exiting,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    TransitionStarted,
+    TransitionFinished,
+    Settled,
+    Blink,
+    Gaze,
+    Paused,
+    Resumed,
+    PointerEntered,
+    PointerLeft,
+    Tapped,
+    Connected,
+    Disconnected,
+    OutputAdded,
+    OutputRemoved,
+    Configured,
+    Reloaded,
+    Exiting,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Animation,
+    Input,
+    Lifecycle,
+}
+
+const KINDS: [EventKind; 17] = [
+    EventKind::TransitionStarted, EventKind::TransitionFinished, EventKind::Settled, EventKind::Blink, EventKind::Gaze,
+    EventKind::Paused, EventKind::Resumed, EventKind::PointerEntered, EventKind::PointerLeft, EventKind::Tapped,
+    EventKind::Connected, EventKind::Disconnected, EventKind::OutputAdded, EventKind::OutputRemoved, EventKind::Configured,
+    EventKind::Reloaded, EventKind::Exiting,
+];
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::TransitionStarted { .. } => EventKind::TransitionStarted,
+            Event::TransitionFinished { .. } => EventKind::TransitionFinished,
+            Event::Settled => EventKind::Settled,
+            Event::Blink => EventKind::Blink,
+            Event::Gaze { .. } => EventKind::Gaze,
+            Event::Paused => EventKind::Paused,
+            Event::Resumed => EventKind::Resumed,
+            Event::PointerEntered { .. } => EventKind::PointerEntered,
+            Event::PointerLeft { .. } => EventKind::PointerLeft,
+            Event::Tapped { .. } => EventKind::Tapped,
+            Event::Connected => EventKind::Connected,
+            Event::Disconnected => EventKind::Disconnected,
+            Event::OutputAdded { .. } => EventKind::OutputAdded,
+            Event::OutputRemoved { .. } => EventKind::OutputRemoved,
+            Event::Configured { .. } => EventKind::Configured,
+            Event::Reloaded { .. } => EventKind::Reloaded,
+            Event::Exiting => EventKind::Exiting,
+        }
+    }
+
+    // One line of the control protocol, e.g. {"event": "tapped", "region": 2}.
+    pub fn to_json(&self) -> Value {
+        let fields = match self {
+            Event::TransitionStarted { expression } | Event::TransitionFinished { expression } => vec![("expression", Value::from(expression.as_str()))],
+            Event::Gaze { x, y } => vec![("x", Value::from(*x)), ("y", Value::from(*y))],
+            Event::PointerEntered { surface } | Event::PointerLeft { surface } => vec![("surface", Value::from(*surface))],
+            Event::Tapped { region } => vec![("region", Value::from(*region))],
+            Event::OutputAdded { name: output } | Event::OutputRemoved { name: output } => vec![("output", Value::from(output.as_str()))],
+            Event::Configured { surface, width, height } => {
+                vec![("surface", Value::from(*surface)), ("width", Value::from(*width)), ("height", Value::from(*height))]
+            }
+            Event::Reloaded { restart } => vec![("restart", Value::from(restart.clone()))],
+            _ => Vec::new(),
+        };
+
+        let mut object = Map::new();
+        object.insert("event".to_string(), Value::from(self.kind().name()));
+        for (key, value) in fields {
+            object.insert(key.to_string(), value);
+        }
+        Value::Object(object)
+    }
+}
+
+impl EventKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EventKind::TransitionStarted => "transition_started",
+            EventKind::TransitionFinished => "transition_finished",
+            EventKind::Settled => "settled",
+            EventKind::Blink => "blink",
+            EventKind::Gaze => "gaze",
+            EventKind::Paused => "paused",
+            EventKind::Resumed => "resumed",
+            EventKind::PointerEntered => "pointer_entered",
+            EventKind::PointerLeft => "pointer_left",
+            EventKind::Tapped => "tapped",
+            EventKind::Connected => "connected",
+            EventKind::Disconnected => "disconnected",
+            EventKind::OutputAdded => "output_added",
+            EventKind::OutputRemoved => "output_removed",
+            EventKind::Configured => "configured",
+            EventKind::Reloaded => "reloaded",
+            EventKind::Exiting => "exiting",
+        }
+    }
+
+    pub fn category(self) -> Category {
+        match self {
+            EventKind::TransitionStarted | EventKind::TransitionFinished | EventKind::Settled | EventKind::Blink
+            | EventKind::Gaze | EventKind::Paused | EventKind::Resumed => Category::Animation,
+            EventKind::PointerEntered | EventKind::PointerLeft | EventKind::Tapped => Category::Input,
+            EventKind::Connected | EventKind::Disconnected | EventKind::OutputAdded | EventKind::OutputRemoved
+            | EventKind::Configured | EventKind::Reloaded | EventKind::Exiting => Category::Lifecycle,
+        }
+    }
+}
+
+impl std::str::FromStr for EventKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KINDS.into_iter().find(|kind| kind.name() == s).ok_or_else(|| format!("Unknown event {}", s))
+    }
+}
+
+impl std::str::FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "animation" => Ok(Category::Animation),
+            "input" => Ok(Category::Input),
+            "lifecycle" => Ok(Category::Lifecycle),
+            _ => Err(format!("Unknown event category {}", s)),
+        }
+    }
+}
+
+// Which events a subscriber gets.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum EventFilter {
+    #[default]
+    All,
+    Kinds(Vec<EventKind>),
+    Categories(Vec<Category>),
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &Event) -> bool {
+        match self {
+            EventFilter::All => true,
+            EventFilter::Kinds(kinds) => kinds.contains(&event.kind()),
+            EventFilter::Categories(categories) => categories.contains(&event.kind().category()),
+        }
+    }
+
+    // Event or category names, like ["tapped", "lifecycle"]. Kinds and categories can be mixed.
+    pub fn parse(names: &[&str]) -> Result<Self, String> {
+        let mut kinds = Vec::new();
+        for name in names {
+            match name.parse::<Category>() {
+                Ok(category) => kinds.extend(KINDS.into_iter().filter(|kind| kind.category() == category)),
+                Err(_) => kinds.push(name.parse::<EventKind>()?),
+            }
+        }
+        Ok(EventFilter::Kinds(kinds))
+    }
+}
+
+pub type SubscriptionId = usize;
+
+struct Subscription {
+    id: SubscriptionId,
+    filter: EventFilter,
+    handler: Box<dyn FnMut(&Event) -> bool>,     // Returns false to unsubscribe
+}
+
+// Hands every published event to the subscribers whose filter matches, in the order they subscribed.
+//
+// Handlers don't get the face, reactions that want to change it send commands through a channel or
+// the control socket instead.
+#[derive(Default)]
+pub struct EventBus {
+    subscriptions: Vec<Subscription>,
+    next_id: SubscriptionId,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self, filter: EventFilter, handler: impl FnMut(&Event) -> bool + 'static) -> SubscriptionId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.subscriptions.push(Subscription { id, filter, handler: Box::new(handler) });
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) {
+        self.subscriptions.retain(|subscription| subscription.id != id);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    pub fn publish(&mut self, event: Event) {
+        self.subscriptions.retain_mut(|subscription| !subscription.filter.matches(&event) || (subscription.handler)(&event));
+    }
+}
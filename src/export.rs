@@ -0,0 +1,163 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use crate::headless::{self, HeadlessError, RenderArgs, RenderOptions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+impl AnimationFormat {
+    // Guess the format from a file extension, `.png` and `.apng` both produce APNG.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "gif" => Some(AnimationFormat::Gif),
+            "png" | "apng" => Some(AnimationFormat::Apng),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub render: RenderOptions,
+    pub format: AnimationFormat,
+    pub loop_count: u16,       // 0 loops forever
+    pub scale: f64,            // Applied to the render size
+    pub colors: usize,         // GIF palette size, 2 to 256
+    pub quality: i32,          // GIF quantizer sampling factor, 1 (best) to 30 (fastest)
+}
+
+impl ExportOptions {
+    // Parse the arguments following the `export` subcommand.
+    pub fn from_args(args: &[String]) -> Result<Self, HeadlessError> {
+        let mut render = RenderArgs::default();
+        let mut format = None;
+        let mut loop_count = 0;
+        let mut scale: f64 = 1.0;
+        let mut colors = 256;
+        let mut quality = 10;
+
+        let mut iter = args.iter();
+        while let Some(flag) = iter.next() {
+            let mut value = || iter.next().ok_or_else(|| HeadlessError::InvalidArgument(format!("{} expects a value", flag)));
+
+            match flag.as_str() {
+                "--format" => format = match value()?.as_str() {
+                    "gif" => Some(AnimationFormat::Gif),
+                    "apng" => Some(AnimationFormat::Apng),
+                    other => return Err(HeadlessError::InvalidArgument(format!("Unknown animation format {}", other))),
+                },
+                "--loop" => loop_count = parse(flag, value()?)?,
+                "--scale" => scale = parse(flag, value()?)?,
+                "--colors" => colors = parse(flag, value()?)?,
+                "--quality" => quality = parse(flag, value()?)?,
+                _ => {
+                    if !render.apply(flag, &mut iter)? {
+                        return Err(HeadlessError::InvalidArgument(format!("Unknown option {}", flag)));
+                    }
+                }
+            }
+        }
+
+        let mut render = render.finish()?;
+        let format = format
+            .or_else(|| AnimationFormat::from_path(&render.out))
+            .ok_or_else(|| HeadlessError::InvalidArgument("Can't infer the animation format from --out, pass --format gif|apng".to_string()))?;
+
+        if scale <= 0.0 || !scale.is_finite() {
+            return Err(HeadlessError::InvalidArgument("--scale must be positive".to_string()));
+        }
+        if !(2..=256).contains(&colors) {
+            return Err(HeadlessError::InvalidArgument("--colors must be between 2 and 256".to_string()));
+        }
+        if !(1..=30).contains(&quality) {
+            return Err(HeadlessError::InvalidArgument("--quality must be between 1 and 30".to_string()));
+        }
+
+        render.width = ((render.width as f64 * scale).round() as u32).max(1);
+        render.height = ((render.height as f64 * scale).round() as u32).max(1);
+
+        Ok(Self { render, format, loop_count, scale, colors, quality })
+    }
+}
+
+fn parse<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, HeadlessError> {
+    value.parse().map_err(|_| HeadlessError::InvalidArgument(format!("Invalid value {} for {}", value, flag)))
+}
+
+// Render the timeline and write it as a single animated file, returns the number of frames.
+pub fn export(options: &ExportOptions) -> Result<usize, HeadlessError> {
+    match options.format {
+        AnimationFormat::Gif => export_gif(options),
+        AnimationFormat::Apng => export_apng(options),
+    }
+}
+
+fn export_gif(options: &ExportOptions) -> Result<usize, HeadlessError> {
+    let encode_err = |err: gif::EncodingError| HeadlessError::Encode(err.to_string());
+    let (width, height) = (options.render.width, options.render.height);
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(HeadlessError::InvalidArgument("GIF frames are limited to 65535x65535".to_string()));
+    }
+
+    let writer = BufWriter::new(File::create(&options.render.out)?);
+    let mut encoder = gif::Encoder::new(writer, width as u16, height as u16, &[]).map_err(encode_err)?;
+    let repeat = match options.loop_count {
+        0 => gif::Repeat::Infinite,
+        n => gif::Repeat::Finite(n),
+    };
+    encoder.set_repeat(repeat).map_err(encode_err)?;
+
+    // GIF delays are in hundredths of a second
+    let delay = (100.0 / options.render.fps).round().max(1.0) as u16;
+
+    headless::render_frames(&options.render, |_, backend| {
+        let rgba = backend.to_rgba();
+        let quantizer = color_quant::NeuQuant::new(options.quality, options.colors, &rgba);
+
+        let mut frame = gif::Frame::from_indexed_pixels(
+            width as u16,
+            height as u16,
+            rgba.chunks_exact(4).map(|pixel| quantizer.index_of(pixel) as u8).collect::<Vec<u8>>(),
+            None,
+        );
+        frame.palette = Some(quantizer.color_map_rgb());
+        frame.delay = delay;
+
+        // GIF transparency is binary, use the most transparent palette entry if it is mostly clear
+        let palette = quantizer.color_map_rgba();
+        frame.transparent = palette
+            .chunks_exact(4)
+            .enumerate()
+            .min_by_key(|(_, color)| color[3])
+            .filter(|(_, color)| color[3] < 0x80)
+            .map(|(index, _)| index as u8);
+
+        encoder.write_frame(&frame).map_err(encode_err)
+    })
+}
+
+fn export_apng(options: &ExportOptions) -> Result<usize, HeadlessError> {
+    let encode_err = |err: png::EncodingError| HeadlessError::Encode(err.to_string());
+    let frame_count = headless::frame_count(&options.render);
+
+    let writer = BufWriter::new(File::create(&options.render.out)?);
+    let mut encoder = png::Encoder::new(writer, options.render.width, options.render.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frame_count as u32, options.loop_count as u32).map_err(encode_err)?;
+
+    // Express the delay in milliseconds so fractional frame rates stay close
+    let delay_ms = (1000.0 / options.render.fps).round().clamp(1.0, u16::MAX as f64) as u16;
+    encoder.set_frame_delay(delay_ms, 1000).map_err(encode_err)?;
+
+    let mut writer = encoder.write_header().map_err(encode_err)?;
+    let count = headless::render_frames(&options.render, |_, backend| {
+        writer.write_image_data(&backend.to_rgba()).map_err(encode_err)
+    })?;
+    writer.finish().map_err(encode_err)?;
+
+    Ok(count)
+}
@@ -0,0 +1,364 @@
+use std::{path::PathBuf, time::{Duration, Instant}};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    animator::{animator::Animator, blink::Blink, gaze::Gaze},
+    cli::RunArgs,
+    config::Config,
+    control::protocol::{Command, Transition},
+    events::{Event, EventBus},
+    hvf::{hvf::HVF, loader::{HVFLoader, BUILTIN_CLASS}},
+    layout::{Layout, Transform},
+    placement::Placement,
+    renderer::{raster::SoftwareRenderer, Color, Renderer, Style},
+    scheduler::FrameScheduler,
+};
+
+// Transition to the reloaded face.
+const RELOAD_DURATION: Duration = Duration::from_millis(300);
+
+// The face independent of any Wayland connection, carried over when reconnecting.
+pub struct FaceState {
+    pub placement: Placement,
+    pub renderer: Box<dyn Renderer>,
+    pub hvf_path: Option<String>,       // Re-read on reload
+    pub hvf: Option<HVFLoader>,         // Expressions that can be switched to by name
+    pub fallback: bool,                 // Showing the built-in face as no HVF file could be loaded
+    pub class: String,                  // HVF class the expressions are looked up in
+    pub expression: String,             // Expression the face is showing or heading to
+    pub farewell: Option<HVF>,
+    pub animator: Animator,             // Keeps the current expression and whatever is queued
+    pub blink: Option<Blink>,
+    pub gaze: Gaze,
+    pub scheduler: FrameScheduler,
+    pub style: Style,
+    pub background: Color,
+    pub layout: Layout,
+    pub events: EventBus,               // Control clients, logging... anyone listening for what happens
+    pub config: Config,                 // Settings in effect
+    pub settings: Option<RunArgs>,      // Where they came from, read again on reload
+    in_transition: bool,                // A requested transition hasn't finished yet
+}
+
+impl FaceState {
+    pub fn new(default_ring: &HVF, placement: Placement) -> Self {
+        // let default_ring = hvf_loader.get("expression", "neutral").unwrap();
+        let mut animator = Animator::new(default_ring);
+        animator.animate(default_ring, Duration::from_secs_f32(0.0));
+        // animator.animate(to_ring);
+
+        Self {
+            placement: placement.clone(),
+            renderer: Box::new(SoftwareRenderer::new()),
+            hvf_path: None,
+            hvf: None,
+            fallback: false,
+            class: "expression".to_string(),
+            expression: "neutral".to_string(),
+            farewell: None,
+            animator,
+            blink: None,
+            gaze: Gaze::default(),
+            scheduler: FrameScheduler::new(None),
+            style: Style::default(),
+            background: Color::TRANSPARENT,
+            layout: Layout::default(),
+            config: Config { placement, ..Config::default() },
+            settings: None,
+            events: EventBus::default(),
+            in_transition: false,
+        }
+    }
+
+    // Look up an expression of the current class.
+    pub fn ring(&self, expression: &str) -> Result<HVF, String> {
+        let hvf = self.hvf.as_ref().ok_or("No HVF file is loaded")?;
+        hvf.get(&self.class, expression)
+            .cloned()
+            .ok_or_else(|| format!("Unknown expression {}", expression))
+    }
+
+    // Only faces with the same number of shapes morph into each other.
+    fn check_morph(&self, expression: &str, ring: &HVF) -> Result<(), String> {
+        match self.animator.target_ring().morphs_into(ring) {
+            true => Ok(()),
+            false => Err(format!("Expression {} has {} shapes, the face has {}", expression, ring.values().len(), self.animator.target_ring().values().len())),
+        }
+    }
+
+    // Run a control command, returns what to answer with. Anything that changes the face needs a redraw.
+    pub fn apply(&mut self, command: Command, now: Instant) -> Result<Value, String> {
+        match command {
+            Command::Set(transition) => {
+                let ring = self.ring(&transition.expression)?;
+                self.check_morph(&transition.expression, &ring)?;
+                self.animator.animate_with(&ring, transition.duration, transition.easing, now);
+                self.started(&transition.expression);
+                Ok(Value::Null)
+            }
+            Command::Sequence { steps, replace } => {
+                // Resolve every step first so a typo doesn't leave half a sequence queued
+                let rings = steps.iter().map(|step| self.ring(&step.expression)).collect::<Result<Vec<HVF>, String>>()?;
+                for (step, ring) in steps.iter().zip(&rings) {
+                    self.check_morph(&step.expression, ring)?;
+                }
+
+                // A settled animator has nothing to queue behind, start right away
+                let start_now = replace || self.animator.is_settled(now);
+                for (i, (step, ring)) in steps.iter().zip(&rings).enumerate() {
+                    let Transition { duration, easing, .. } = *step;
+                    if i == 0 && start_now {
+                        self.animator.animate_with(ring, duration, easing, now);
+                    } else {
+                        self.animator.enqueue_with(ring, duration, easing);
+                    }
+                }
+                self.started(&steps[steps.len() - 1].expression);
+                Ok(Value::Null)
+            }
+            Command::Blend { weights, duration, easing } => {
+                let rings = weights.iter().map(|(expression, _)| self.ring(expression)).collect::<Result<Vec<HVF>, String>>()?;
+                for ((expression, _), ring) in weights.iter().zip(&rings) {
+                    self.check_morph(expression, ring)?;
+                }
+                let weighted: Vec<(&HVF, f64)> = rings.iter().zip(&weights).map(|(ring, (_, weight))| (ring, *weight)).collect();
+
+                let blended = Animator::blend(&weighted).ok_or("Nothing to blend")?;
+                self.animator.animate_with(&blended, duration, easing, now);
+                self.started("blend");
+                Ok(Value::Null)
+            }
+            Command::Blink => {
+                let blink = self.blink.as_mut().ok_or("Blinking is turned off")?;
+                if !blink.trigger(now, &mut self.animator) {
+                    return Err("The face is busy animating".to_string());
+                }
+                self.publish(Event::Blink);
+                Ok(Value::Null)
+            }
+            Command::Gaze { x, y, duration, easing } => {
+                self.gaze.look_at((x, y), duration, easing, now);
+                let (x, y) = self.gaze.target();
+                self.publish(Event::Gaze { x, y });
+                Ok(Value::Null)
+            }
+            Command::Pause => {
+                self.animator.pause(now);
+                self.forward_animator_events(now);
+                Ok(Value::Null)
+            }
+            Command::Resume => {
+                self.animator.resume(now);
+                self.forward_animator_events(now);
+                Ok(Value::Null)
+            }
+            Command::State => Ok(object([("state", self.state(now))])),
+            Command::List => {
+                let hvf = self.hvf.as_ref().ok_or("No HVF file is loaded")?;
+                Ok(object([("class", Value::from(self.class.as_str())), ("expressions", Value::from(hvf.ids(&self.class)))]))
+            }
+            // Subscriptions belong to the connection, the server takes care of them
+            Command::Subscribe(_) => Err("Subscribing is only possible over a connection".to_string()),
+        }
+    }
+
+    pub fn state(&self, now: Instant) -> Value {
+        let (x, y) = self.gaze.target();
+        object([
+            ("expression", Value::from(self.expression.as_str())),
+            ("class", Value::from(self.class.as_str())),
+            ("animating", Value::from(!self.animator.is_settled(now))),
+            ("paused", Value::from(self.animator.is_paused())),
+            ("queued", Value::from(self.animator.queued())),
+            ("gaze", Value::from(vec![x, y])),
+        ])
+    }
+
+    // Nothing moves anymore, neither the expression nor the gaze.
+    pub fn is_settled(&self, now: Instant) -> bool {
+        self.animator.is_settled(now) && self.gaze.is_settled(now)
+    }
+
+    // Design space to surface transform for a surface of `width` x `height` pixels, following the gaze.
+    pub fn transform(&self, width: u32, height: u32, scale: f64, now: Instant) -> Transform {
+        let mut transform = self.layout.transform(width, height, scale);
+        let (x, y) = self.gaze.offset(now);
+        transform.offset_x += x * transform.scale_x;
+        transform.offset_y += y * transform.scale_y;
+        transform
+    }
+
+    fn started(&mut self, expression: &str) {
+        self.expression = expression.to_string();
+        self.in_transition = true;
+        self.publish(Event::TransitionStarted { expression: expression.to_string() });
+    }
+
+    pub fn publish(&mut self, event: Event) {
+        if !self.events.is_empty() {
+            self.events.publish(event);
+        }
+    }
+
+    // A requested transition finishes when the animator settles, the animator's own events are passed on.
+    fn forward_animator_events(&mut self, now: Instant) {
+        for event in self.animator.take_events(now) {
+            if event == Event::Settled && self.in_transition {
+                self.in_transition = false;
+                self.publish(Event::TransitionFinished { expression: self.expression.clone() });
+            }
+            self.publish(event);
+        }
+    }
+
+    // Blink and report finished transitions, returns true if the face needs to be drawn.
+    pub fn poll(&mut self, now: Instant) -> bool {
+        // Report what finished before a blink starts animating again
+        self.forward_animator_events(now);
+
+        let mut wake = false;
+        if let Some(blink) = self.blink.as_mut() {
+            if blink.poll(now, &mut self.animator) {
+                wake = true;
+                self.publish(Event::Blink);
+            }
+        }
+        wake
+    }
+
+    // Earliest time `poll` has something to do.
+    pub fn next_wakeup(&self) -> Option<Instant> {
+        let blink = self.blink.as_ref().map(Blink::next_at);
+        blink.into_iter().chain(self.animator.next_event_at()).min()
+    }
+
+    // Take over the settings that can change while running, returns the keys of those that need a restart.
+    // The placement is up to the layer, which knows about surfaces.
+    pub fn configure(&mut self, config: Config) -> Vec<&'static str> {
+        let old = &self.config;
+        let restart: Vec<&'static str> = [
+            ("ipc.control", old.ipc.control != config.ipc.control),
+            ("ipc.socket", old.ipc.socket_path() != config.ipc.socket_path()),
+            ("ipc.dbus", old.ipc.dbus != config.ipc.dbus),
+            ("session.supervise", old.supervise != config.supervise),
+            ("session.window", old.window != config.window),
+        ].into_iter().filter(|(_, changed)| *changed).map(|(key, _)| key).collect();
+
+        // The built-in face has its own class
+        self.class = match self.fallback {
+            true => BUILTIN_CLASS.to_string(),
+            false => config.class.clone(),
+        };
+        self.style = config.theme.style;
+        self.background = config.theme.background;
+        self.layout = config.layout;
+        self.scheduler.set_fps_cap(config.fps_cap);
+
+        let blink_ring = self.hvf.as_ref().and_then(|hvf| hvf.get(&self.class, "blink"));
+        match (blink_ring, self.blink.as_mut()) {
+            (Some(ring), Some(blink)) if config.blink.enabled => {
+                blink.set_ring(ring);
+                blink.set_timing(config.blink.interval, config.blink.close, config.blink.open);
+            }
+            (Some(ring), None) if config.blink.enabled => {
+                let mut blink = Blink::new(ring, config.blink.interval);
+                blink.set_timing(config.blink.interval, config.blink.close, config.blink.open);
+                self.blink = Some(blink);
+            }
+            (None, _) if config.blink.enabled => {
+                tracing::warn!("There's no {} expression blink, not blinking", self.class);
+                self.blink = None;
+            }
+            _ => self.blink = None,
+        }
+
+        // Eyes close on the way out unless the face has its own goodbye, whichever morphs from the current expression
+        if let Some(hvf) = &self.hvf {
+            let current = self.animator.target_ring();
+            self.farewell = [hvf.get(&self.class, "goodbye"), blink_ring].into_iter().flatten()
+                .find(|ring| current.morphs_into(ring))
+                .cloned();
+        }
+
+        self.config = config;
+        restart
+    }
+
+    // Re-read the settings and the HVF file, and move over to the same expression in it. Whatever is broken
+    // is kept as it was. Returns the keys of changed settings that need a restart.
+    pub fn reload(&mut self, now: Instant) -> Vec<&'static str> {
+        let config = match self.settings.as_ref().map(RunArgs::resolve) {
+            Some(Ok(config)) => config,
+            Some(Err(err)) => {
+                tracing::error!("Keeping the current settings, the new ones are invalid: {}", err);
+                self.config.clone()
+            }
+            None => self.config.clone(),
+        };
+
+        // The configured files, or the one in use if there are none
+        let paths: Vec<PathBuf> = match config.hvf.is_empty() {
+            true => self.hvf_path.iter().map(PathBuf::from).collect(),
+            false => config.hvf.clone(),
+        };
+        let loaded = paths.iter().find_map(|path| match HVFLoader::new(&path.to_string_lossy()) {
+            Ok(loader) => Some((path, loader)),
+            Err(err) => {
+                tracing::error!("Failed to reload {}: {:?}", path.display(), err);
+                None
+            }
+        });
+
+        match loaded {
+            Some((path, loader)) => {
+                tracing::info!("Reloaded {}", path.display());
+
+                // Coming back from the built-in face, its error expression isn't what was asked for
+                let current = match self.fallback {
+                    true => None,
+                    false => loader.get(&config.class, &self.expression),
+                };
+                let found = current.map(|ring| (self.expression.clone(), ring))
+                    .or_else(|| loader.get(&config.class, &config.expression).map(|ring| (config.expression.clone(), ring)))
+                    .or_else(|| loader.get(&config.class, "neutral").map(|ring| ("neutral".to_string(), ring)));
+                match found {
+                    Some((expression, ring)) => {
+                        // Faces with a different number of shapes can't morph into each other
+                        match self.animator.target_ring().morphs_into(ring) {
+                            true => self.animator.animate_at(ring, RELOAD_DURATION, now),
+                            false => {
+                                self.animator = Animator::new(ring);
+                                self.animator.animate_at(ring, Duration::ZERO, now);
+                            }
+                        }
+                        self.expression = expression;
+                        self.fallback = false;
+                        self.hvf_path = Some(path.to_string_lossy().into_owned());
+                        self.hvf = Some(loader);
+                    }
+                    None if self.fallback => tracing::error!("{} has no {} expression {}, still showing the built-in face", path.display(), config.class, config.expression),
+                    None => {
+                        self.hvf_path = Some(path.to_string_lossy().into_owned());
+                        self.hvf = Some(loader);
+                    }
+                }
+            }
+            None if self.fallback => tracing::error!("Still showing the built-in face"),
+            None if !paths.is_empty() => tracing::error!("Keeping the current face"),
+            None => {}
+        }
+
+        let restart = self.configure(config);
+        if !restart.is_empty() {
+            tracing::warn!("Restart hyogen-ui to apply {}", restart.join(", "));
+        }
+        self.publish(Event::Reloaded { restart: restart.iter().map(|key| key.to_string()).collect() });
+        restart
+    }
+}
+
+// A JSON object from key and value pairs.
+fn object<const N: usize>(fields: [(&str, Value); N]) -> Value {
+    Value::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect::<Map<String, Value>>())
+}
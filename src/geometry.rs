@@ -0,0 +1,141 @@
+use std::ops::Index;
+
+// A point in design space.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub const fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    pub fn distance(self, other: Point) -> f64 {
+        self.distance_squared(other).sqrt()
+    }
+
+    pub fn distance_squared(self, other: Point) -> f64 {
+        let (dx, dy) = (self.x - other.x, self.y - other.y);
+        dx * dx + dy * dy
+    }
+
+    // The point `t` of the way to `other`, `t` isn't clamped.
+    pub fn lerp(self, other: Point, t: f64) -> Point {
+        Point::new(self.x + (other.x - self.x) * t, self.y + (other.y - self.y) * t)
+    }
+}
+
+impl From<(f64, f64)> for Point {
+    fn from((x, y): (f64, f64)) -> Self {
+        Point::new(x, y)
+    }
+}
+
+impl From<[f64; 2]> for Point {
+    fn from([x, y]: [f64; 2]) -> Self {
+        Point::new(x, y)
+    }
+}
+
+// A closed path, the last point connects back to the first. Shapes hand them out as slices of their buffer.
+pub type Path = [Point];
+
+// Length all the way around, back to the first point.
+pub fn perimeter(path: &Path) -> f64 {
+    let Some(&last) = path.last() else {
+        return 0.0;
+    };
+    let mut prev = last;
+    path.iter().map(|&point| {
+        let length = prev.distance(point);
+        prev = point;
+        length
+    }).sum()
+}
+
+// Closed paths stored back to back in a single buffer, an expression or a frame. Filling one that's been
+// cleared reuses its memory, so animating doesn't allocate once the buffers have grown to size.
+#[derive(Debug, PartialEq, Default)]
+pub struct Shape {
+    points: Vec<Point>,
+    ends: Vec<usize>,          // One past the last point of each path
+}
+
+impl Shape {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(paths: usize, points: usize) -> Self {
+        Self { points: Vec::with_capacity(points), ends: Vec::with_capacity(paths) }
+    }
+
+    // Append a path.
+    pub fn push(&mut self, points: impl IntoIterator<Item = Point>) {
+        self.points.extend(points);
+        self.ends.push(self.points.len());
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.ends.clear();
+    }
+
+    // Number of paths.
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    pub fn path(&self, index: usize) -> &Path {
+        let start = match index {
+            0 => 0,
+            _ => self.ends[index - 1],
+        };
+        &self.points[start..self.ends[index]]
+    }
+
+    pub fn paths(&self) -> impl DoubleEndedIterator<Item = &Path> + ExactSizeIterator + '_ {
+        (0..self.len()).map(|index| self.path(index))
+    }
+
+    // Every point of every path.
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+}
+
+impl Clone for Shape {
+    fn clone(&self) -> Self {
+        Self { points: self.points.clone(), ends: self.ends.clone() }
+    }
+
+    // Kept frames are overwritten with every new one, without allocating
+    fn clone_from(&mut self, source: &Self) {
+        self.points.clone_from(&source.points);
+        self.ends.clone_from(&source.ends);
+    }
+}
+
+impl Index<usize> for Shape {
+    type Output = Path;
+
+    fn index(&self, index: usize) -> &Path {
+        self.path(index)
+    }
+}
+
+impl<P: AsRef<Path>> FromIterator<P> for Shape {
+    fn from_iter<I: IntoIterator<Item = P>>(iter: I) -> Self {
+        let mut shape = Shape::new();
+        for path in iter {
+            shape.push(path.as_ref().iter().copied());
+        }
+        shape
+    }
+}
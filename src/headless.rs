@@ -0,0 +1,343 @@
+use std::{fs::{self, File}, io::{self, BufWriter, Write}, path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use crate::{
+    animator::animator::Animator,
+    geometry::Shape,
+    hvf::loader::{HVFLoader, HvfError},
+    layout::Layout,
+    renderer::{memory::MemoryBackend, raster::SoftwareRenderer, Color, Frame, Style}
+};
+
+// Longest duration accepted anywhere, far enough from overflowing an Instant.
+pub const MAX_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+// Largest width or height accepted for offline rendering, a frame that size is already 256 MiB.
+pub const MAX_SIZE: u32 = 8192;
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    Hvf(HvfError),
+    MissingExpression(String),
+    InvalidArgument(String),
+    Io(io::Error),
+    Encode(String),
+}
+
+impl From<io::Error> for HeadlessError {
+    fn from(err: io::Error) -> Self {
+        HeadlessError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Ppm,
+}
+
+// One morph towards an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub to: String,
+    pub duration: Duration,
+}
+
+// The expression to start from followed by the morphs to play, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timeline {
+    pub from: String,
+    pub steps: Vec<Step>,
+}
+
+impl Timeline {
+    pub fn single(from: &str, to: &str, duration: Duration) -> Self {
+        Self {
+            from: from.to_string(),
+            steps: vec![Step { to: to.to_string(), duration }],
+        }
+    }
+
+    // Parse `neutral,happy:0.5,blink:200ms`, the first entry is the starting expression.
+    pub fn parse(spec: &str) -> Result<Self, HeadlessError> {
+        let mut entries = spec.split(',').map(str::trim);
+        let from = entries.next().filter(|from| !from.is_empty())
+            .ok_or_else(|| HeadlessError::InvalidArgument("Sequence is empty".to_string()))?;
+
+        let steps = entries
+            .map(|entry| {
+                let (to, duration) = entry.split_once(':')
+                    .ok_or_else(|| HeadlessError::InvalidArgument(format!("Sequence step {} is missing a duration", entry)))?;
+                Ok(Step { to: to.to_string(), duration: parse_duration(duration)? })
+            })
+            .collect::<Result<Vec<Step>, HeadlessError>>()?;
+
+        if steps.is_empty() {
+            return Err(HeadlessError::InvalidArgument("Sequence needs at least one step".to_string()));
+        }
+        Ok(Self { from: from.to_string(), steps })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.steps.iter().map(|step| step.duration).sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub hvf: PathBuf,
+    pub class: String,
+    pub timeline: Timeline,
+    pub fps: f64,
+    pub width: u32,
+    pub height: u32,
+    pub out: PathBuf,
+    pub format: ImageFormat,
+    pub background: Color,
+    pub style: Style,
+    pub layout: Layout,
+}
+
+impl RenderOptions {
+    // Parse the arguments following the `render` subcommand.
+    pub fn from_args(args: &[String]) -> Result<Self, HeadlessError> {
+        let mut parser = RenderArgs::default();
+        let mut iter = args.iter();
+        while let Some(flag) = iter.next() {
+            if !parser.apply(flag, &mut iter)? {
+                return Err(HeadlessError::InvalidArgument(format!("Unknown option {}", flag)));
+            }
+        }
+
+        parser.finish()
+    }
+}
+
+// Accumulates the flags shared by every offline rendering subcommand.
+#[derive(Debug)]
+pub struct RenderArgs {
+    hvf: Option<PathBuf>,
+    class: String,
+    from: String,
+    to: Option<String>,
+    duration: Duration,
+    sequence: Option<Timeline>,
+    fps: f64,
+    size: (u32, u32),
+    out: PathBuf,
+    format: ImageFormat,
+    background: Color,
+    layout: Layout,
+}
+
+impl Default for RenderArgs {
+    fn default() -> Self {
+        Self {
+            hvf: None,
+            class: "expression".to_string(),
+            from: "neutral".to_string(),
+            to: None,
+            duration: Duration::from_secs_f64(0.5),
+            sequence: None,
+            fps: 60.0,
+            size: (800, 480),
+            out: PathBuf::from("frames"),
+            // PNG needs the export feature, PPM is always there
+            format: if cfg!(feature = "export") { ImageFormat::Png } else { ImageFormat::Ppm },
+            background: Color::BLACK,
+            layout: Layout::default(),
+        }
+    }
+}
+
+impl RenderArgs {
+    // Consume `flag` (and its value) if it is a rendering flag, returns false for unknown flags.
+    pub fn apply<'a>(&mut self, flag: &str, iter: &mut impl Iterator<Item = &'a String>) -> Result<bool, HeadlessError> {
+        let mut value = || iter.next().ok_or_else(|| HeadlessError::InvalidArgument(format!("{} expects a value", flag)));
+
+        match flag {
+            "--hvf" => self.hvf = Some(PathBuf::from(value()?)),
+            "--class" => self.class = value()?.clone(),
+            "--from" => self.from = value()?.clone(),
+            "--to" => self.to = Some(value()?.clone()),
+            "--duration" => self.duration = parse_duration(value()?)?,
+            "--sequence" => self.sequence = Some(Timeline::parse(value()?)?),
+            "--fps" => self.fps = parse_number(flag, value()?)?,
+            "--size" => self.size = parse_size(value()?)?,
+            "--out" => self.out = PathBuf::from(value()?),
+            "--format" => self.format = match value()?.as_str() {
+                "png" => ImageFormat::Png,
+                "ppm" => ImageFormat::Ppm,
+                other => return Err(HeadlessError::InvalidArgument(format!("Unknown image format {}", other))),
+            },
+            "--layout" => self.layout.mode = value()?.parse().map_err(HeadlessError::InvalidArgument)?,
+            "--align" => self.layout.align = value()?.parse().map_err(HeadlessError::InvalidArgument)?,
+            "--padding" => self.layout.padding = value()?.parse().map_err(HeadlessError::InvalidArgument)?,
+            "--background" => {
+                let hex = value()?;
+                self.background = Color::from_hex(hex)
+                    .ok_or_else(|| HeadlessError::InvalidArgument(format!("Invalid color {}", hex)))?;
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    pub fn finish(self) -> Result<RenderOptions, HeadlessError> {
+        let hvf = self.hvf.ok_or_else(|| HeadlessError::InvalidArgument("--hvf is required".to_string()))?;
+        let timeline = match (self.sequence, self.to) {
+            (Some(sequence), None) => sequence,
+            (None, Some(to)) => Timeline::single(&self.from, &to, self.duration),
+            (Some(_), Some(_)) => return Err(HeadlessError::InvalidArgument("--sequence and --to are mutually exclusive".to_string())),
+            (None, None) => return Err(HeadlessError::InvalidArgument("Either --to or --sequence is required".to_string())),
+        };
+        if self.fps <= 0.0 || !self.fps.is_finite() {
+            return Err(HeadlessError::InvalidArgument("--fps must be positive".to_string()));
+        }
+
+        Ok(RenderOptions {
+            hvf,
+            class: self.class,
+            timeline,
+            fps: self.fps,
+            width: self.size.0,
+            height: self.size.1,
+            out: self.out,
+            format: self.format,
+            background: self.background,
+            style: Style::default(),
+            layout: self.layout,
+        })
+    }
+}
+
+pub fn parse_duration(value: &str) -> Result<Duration, HeadlessError> {
+    // Plain numbers are seconds, `ms` and `s` suffixes are accepted as well
+    let (number, scale) = if let Some(ms) = value.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(s) = value.strip_suffix('s') {
+        (s, 1.0)
+    } else {
+        (value, 1.0)
+    };
+
+    let seconds: f64 = parse_number("duration", number)?;
+    duration_from_secs(seconds * scale).ok_or_else(|| HeadlessError::InvalidArgument(format!("Invalid duration {}", value)))
+}
+
+// None for negative, non-finite and too long durations, Duration::from_secs_f64 panics on some of them.
+pub fn duration_from_secs(seconds: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds).ok().filter(|duration| *duration <= MAX_DURATION)
+}
+
+pub fn parse_size(value: &str) -> Result<(u32, u32), HeadlessError> {
+    let invalid = || HeadlessError::InvalidArgument(format!("Invalid size {}, expected WIDTHxHEIGHT", value));
+    let (w, h) = value.split_once('x').ok_or_else(invalid)?;
+    let width: u32 = w.parse().map_err(|_| invalid())?;
+    let height: u32 = h.parse().map_err(|_| invalid())?;
+
+    if width == 0 || height == 0 {
+        return Err(invalid());
+    }
+    if width > MAX_SIZE || height > MAX_SIZE {
+        return Err(HeadlessError::InvalidArgument(format!("Invalid size {}, at most {}x{}", value, MAX_SIZE, MAX_SIZE)));
+    }
+    Ok((width, height))
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, HeadlessError> {
+    value.parse().map_err(|_| HeadlessError::InvalidArgument(format!("Invalid value {} for {}", value, name)))
+}
+
+// Number of frames `render_frames` produces for the given options.
+pub fn frame_count(options: &RenderOptions) -> usize {
+    // One frame per tick, plus the final frame showing the last expression
+    (options.timeline.duration().as_secs_f64() * options.fps).round() as usize + 1
+}
+
+// Drives the animator on a virtual clock, handing every rasterized frame to `sink`.
+pub fn render_frames<F>(options: &RenderOptions, mut sink: F) -> Result<usize, HeadlessError>
+where
+    F: FnMut(usize, &MemoryBackend) -> Result<(), HeadlessError>,
+{
+    let loader = HVFLoader::new(&options.hvf.to_string_lossy()).map_err(HeadlessError::Hvf)?;
+    let lookup = |id: &str| loader.get(&options.class, id).ok_or_else(|| HeadlessError::MissingExpression(id.to_string()));
+
+    // Resolve everything up front so a typo fails before any frame is written
+    let mut rings = vec![lookup(&options.timeline.from)?];
+    let mut from = &options.timeline.from;
+    for step in &options.timeline.steps {
+        let ring = lookup(&step.to)?;
+        if !rings[rings.len() - 1].morphs_into(ring) {
+            return Err(HeadlessError::InvalidArgument(format!("{} and {} have a different number of shapes, they can't morph", from, step.to)));
+        }
+        rings.push(ring);
+        from = &step.to;
+    }
+
+    let start = Instant::now();
+    let mut animator = Animator::new(rings[0]);
+    animator.animate_at(rings[0], Duration::ZERO, start);
+    for (step, ring) in options.timeline.steps.iter().zip(&rings[1..]) {
+        animator.enqueue(ring, step.duration);
+    }
+
+    let mut renderer = SoftwareRenderer::new();
+    let mut backend = MemoryBackend::new(options.width, options.height);
+
+    let transform = options.layout.transform(options.width, options.height, 1.0);
+    let mut frame = Frame::new(Shape::new(), vec![options.style], options.background).with_transform(transform);
+    let count = frame_count(options);
+    for i in 0..count {
+        let time = start + Duration::from_secs_f64(i as f64 / options.fps);
+        animator.get_path_into(time, &mut frame.paths);
+
+        backend.draw(&mut renderer, &frame);
+        sink(i, &backend)?;
+    }
+
+    Ok(count)
+}
+
+// Render the transition described by `options` as numbered image files.
+pub fn render_to_files(options: &RenderOptions) -> Result<usize, HeadlessError> {
+    fs::create_dir_all(&options.out)?;
+
+    render_frames(options, |index, backend| {
+        let extension = match options.format {
+            ImageFormat::Png => "png",
+            ImageFormat::Ppm => "ppm",
+        };
+        let path = options.out.join(format!("frame_{:05}.{}", index, extension));
+
+        match options.format {
+            #[cfg(feature = "export")]
+            ImageFormat::Png => write_png(&path, backend),
+            #[cfg(not(feature = "export"))]
+            ImageFormat::Png => Err(HeadlessError::Encode("PNG output needs the export feature, use --format ppm".to_string())),
+            ImageFormat::Ppm => write_ppm(&path, backend),
+        }
+    })
+}
+
+#[cfg(feature = "export")]
+pub fn write_png(path: &Path, backend: &MemoryBackend) -> Result<(), HeadlessError> {
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, backend.width(), backend.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(|e| HeadlessError::Encode(e.to_string()))?;
+    writer.write_image_data(&backend.to_rgba()).map_err(|e| HeadlessError::Encode(e.to_string()))
+}
+
+pub fn write_ppm(path: &Path, backend: &MemoryBackend) -> Result<(), HeadlessError> {
+    // Binary P6, alpha is dropped
+    let mut writer = BufWriter::new(File::create(path)?);
+    write!(writer, "P6\n{} {}\n255\n", backend.width(), backend.height())?;
+
+    for pixel in backend.to_rgba().chunks_exact(4) {
+        writer.write_all(&pixel[..3])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
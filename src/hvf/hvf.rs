@@ -1,55 +1,59 @@
 use serde_json::Value;
 
+use crate::geometry::{Point, Shape};
+
 
 #[derive(Debug, Clone)]
 pub struct HVF {
     // pub values: Vec<Value>
-    pub values: Vec<Vec<Vec<f64>>>,
-    pub len: usize
+    pub values: Shape,
 }
 
 
 impl HVF {
-    pub fn new(value: &Vec<Value>) -> Self {
-        let sub_paths = value.len();
+    pub fn new(value: &[Value]) -> Self {
+        Self::try_new(value).unwrap()
+    }
 
-        let mut values: Vec<Vec<Vec<f64>>> = Vec::new();
+    // Like `new`, for data that may not be paths of points.
+    pub fn try_new(value: &[Value]) -> Result<Self, String> {
+        let mut values = Shape::with_capacity(value.len(), value.iter().filter_map(Value::as_array).map(Vec::len).sum());
         
         for sub_path in value {
-            let path = Self::convert_to_vec_of_vec(sub_path.as_array().unwrap().to_owned()).unwrap();
+            let points = sub_path.as_array().ok_or("Expected an array of points".to_string())?;
+            if points.len() < 2 {
+                return Err("Expected paths of at least two [x, y] points".to_string());
+            }
+            let path = points.iter().map(Self::convert_to_point).collect::<Result<Vec<Point>, String>>()?;
             
             values.push(path);
         }
 
-        Self {
-            values,
-            len: sub_paths
-        }
+        Ok(Self { values })
+    }
+
+    pub fn from_paths(values: Shape) -> Self {
+        Self { values }
     }
 
-    fn convert_to_vec_of_vec(values: Vec<Value>) -> Result<Vec<Vec<f64>>, String> {
-        values
-            .into_iter()
-            .map(|value| {
-                if let Value::Array(array) = value {
-                    array
-                        .into_iter()
-                        .map(|inner_value| {
-                            if let Value::Number(num) = inner_value {
-                                num.as_f64().ok_or("Failed to convert number to f64".to_string())
-                            } else {
-                                Err("Expected a number".to_string())
-                            }
-                        })
-                        .collect::<Result<Vec<f64>, String>>()
-                } else {
-                    Err("Expected an array".to_string())
-                }
-            })
-            .collect()
+    fn convert_to_point(value: &Value) -> Result<Point, String> {
+        let coordinates = match value.as_array().map(Vec::as_slice) {
+            Some([x, y]) => [x, y],
+            _ => return Err("Expected paths of at least two [x, y] points".to_string()),
+        };
+        let [x, y] = coordinates.map(|coordinate| match coordinate {
+            Value::Number(num) => num.as_f64().ok_or("Failed to convert number to f64".to_string()),
+            _ => Err("Expected a number".to_string()),
+        });
+        Ok(Point::new(x?, y?))
     }
  
-    pub fn values(&self) -> &Vec<Vec<Vec<f64>>> {
+    pub fn values(&self) -> &Shape {
         &self.values
     }
-}
\ No newline at end of file
+
+    // Only expressions with the same number of paths morph into each other.
+    pub fn morphs_into(&self, other: &HVF) -> bool {
+        self.values.len() == other.values.len()
+    }
+}
@@ -4,6 +4,9 @@ use serde_json::Value;
 
 use super::hvf::HVF;
 
+// Class of the built-in face's expressions.
+pub const BUILTIN_CLASS: &str = "expression";
+
 #[derive(Debug)]
 pub enum HvfError {
     InvalidPath,
@@ -11,6 +14,7 @@ pub enum HvfError {
     ParseError,
 }
 
+#[derive(Default)]
 pub struct HVFLoader {
     // values: HashMap<String, HVF>,
     values: HashMap<String, HashMap<String, HVF>>
@@ -34,34 +38,68 @@ impl HVFLoader {
 
         // Load the HVF file
         let content = fs::read_to_string(path).map_err(|_| HvfError::ParseError)?;
+        Self::parse(&content)
+    }
 
+    // An HVF pack that's already in memory, e.g. one embedded with `include_hvf!`.
+    pub fn parse(content: &str) -> Result<Self, HvfError> {
         // Parse the HVF file as JSON
-        let data: Value = serde_json::from_str(&content).map_err(|_| HvfError::ParseError)?;
+        let data: Value = serde_json::from_str(content).map_err(|_| HvfError::ParseError)?;
+        let obj = data.as_object().ok_or(HvfError::ParseError)?;
 
         let mut values = HashMap::new();
 
         // Parse through each class in the HVF structure
-        if let Some(obj) = data.as_object() {
-            for (class_id, class_data) in obj.iter() {
-                let mut hvf_values = HashMap::new();
-
-                // Populate the `values` HashMap from the JSON object
-                if let Some(obj) = class_data.as_object() {
-                    for (id, data) in obj.iter() {
-                        hvf_values.insert(id.clone(), HVF::new(data.as_array().unwrap()));
-                    }
-                }
-
-                // let hvf = HVF::new(class_data, class_id.clone());
-                values.insert(class_id.clone(), hvf_values);
+        for (class_id, class_data) in obj.iter() {
+            let mut hvf_values = HashMap::new();
+
+            // Populate the `values` HashMap from the JSON object, anything malformed fails the whole pack
+            let class_obj = class_data.as_object().ok_or(HvfError::ParseError)?;
+            for (id, data) in class_obj.iter() {
+                let paths = data.as_array().ok_or(HvfError::ParseError)?;
+                let hvf = HVF::try_new(paths).map_err(|err| {
+                    tracing::error!("Invalid {} expression {}: {}", class_id, id, err);
+                    HvfError::ParseError
+                })?;
+                hvf_values.insert(id.clone(), hvf);
             }
+
+            values.insert(class_id.clone(), hvf_values);
         }
 
         Ok(Self { values })
     }
 
+    // The face compiled into hyogen-ui, shown when no HVF file can be loaded. It has neutral, blink
+    // and error expressions in the `BUILTIN_CLASS` class.
+    pub fn builtin() -> Self {
+        crate::include_hvf!("../../assets/default.hvf")
+    }
+
+    pub fn ids(&self, class: &str) -> Vec<&str> {
+        // Every id of a class, sorted so listings are stable
+        let mut ids: Vec<&str> = self.values.get(class).map(|hvf| hvf.keys().map(String::as_str).collect()).unwrap_or_default();
+        ids.sort_unstable();
+        ids
+    }
+
+    // Add or replace an expression, e.g. one imported from SVG into an empty `HVFLoader::default()`.
+    pub fn insert(&mut self, class: &str, id: &str, hvf: HVF) {
+        self.values.entry(class.to_string()).or_default().insert(id.to_string(), hvf);
+    }
+
     pub fn get(&self, class: &str, id: &str) -> Option<&HVF> {
         // Retrieve the HVF by class, then retrieve the value by ID
         self.values.get(class).and_then(|hvf| hvf.get(id))
     }
 }
+
+// Embed an HVF pack in the binary, e.g. `include_hvf!("../faces/robot.hvf")`. The path is relative to the
+// file using the macro, like `include_str!`. Panics if the pack is invalid, which any run will catch.
+#[macro_export]
+macro_rules! include_hvf {
+    ($path:expr) => {
+        $crate::hvf::loader::HVFLoader::parse(include_str!($path))
+            .unwrap_or_else(|err| panic!("{} is not a valid HVF pack: {:?}", $path, err))
+    };
+}
@@ -1,3 +1,6 @@
 // pub use self::loader::*;
 pub mod loader;
-pub mod hvf;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod hvf;
+#[cfg(feature = "svg")]
+pub mod svg;
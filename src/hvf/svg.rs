@@ -0,0 +1,272 @@
+use std::{fs, path::Path};
+
+use quick_xml::{events::{BytesStart, Event}, Reader, XmlVersion};
+
+use super::hvf::HVF;
+use crate::geometry::Point;
+
+// Segments a Bézier curve is flattened into.
+const CURVE_STEPS: usize = 8;
+// Points around circles and ellipses.
+const ELLIPSE_POINTS: usize = 32;
+
+type Points = Vec<Point>;
+
+// Read the shapes of an SVG drawing as an HVF expression, one closed path per shape or `<path>` subpath,
+// in document order. Coordinates are taken as they are, so draw on an 800x480 canvas like HVF files.
+// Curves are flattened into line segments, arcs and transforms aren't supported.
+pub fn import(svg: &str) -> Result<HVF, String> {
+    let mut reader = Reader::from_str(svg);
+    let mut paths = Vec::new();
+    let mut hidden = 0;     // Depth inside <defs> and the like, nothing there is drawn
+
+    loop {
+        match reader.read_event().map_err(|err| format!("Invalid SVG at {}: {}", reader.buffer_position(), err))? {
+            Event::Start(element) if hidden > 0 || is_hidden(&element) => hidden += 1,
+            Event::End(_) if hidden > 0 => hidden -= 1,
+            Event::Start(element) | Event::Empty(element) if hidden == 0 => paths.extend(shape(&element)?),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if paths.is_empty() {
+        return Err("The SVG has no shapes".to_string());
+    }
+    Ok(HVF::from_paths(paths.into_iter().collect()))
+}
+
+pub fn import_file(path: &Path) -> Result<HVF, String> {
+    let svg = fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    import(&svg).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+fn is_hidden(element: &BytesStart) -> bool {
+    matches!(element.local_name().as_ref(), b"defs" | b"clipPath" | b"mask" | b"symbol" | b"marker" | b"pattern")
+}
+
+// The paths of one element, none for elements that aren't shapes.
+fn shape(element: &BytesStart) -> Result<Vec<Points>, String> {
+    let name = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+    let attribute = |key: &str| -> Result<Option<String>, String> {
+        match element.try_get_attribute(key).map_err(|err| format!("<{}>: {}", name, err))? {
+            Some(attr) => attr.normalized_value(XmlVersion::Implicit1_0)
+                .map(|value| Some(value.into_owned()))
+                .map_err(|err| format!("<{}> {}: {}", name, key, err)),
+            None => Ok(None),
+        }
+    };
+    let number = |key: &str| -> Result<f64, String> {
+        match attribute(key)? {
+            Some(value) => value.trim().trim_end_matches("px").parse().map_err(|_| format!("<{}> {} is not a number: {}", name, key, value)),
+            None => Ok(0.0),
+        }
+    };
+
+    // Groups included, otherwise the shapes would silently end up in the wrong place
+    if attribute("transform")?.is_some() {
+        return Err(format!("<{}> has a transform, apply it to the shapes first", name));
+    }
+
+    let paths = match name.as_str() {
+        "path" => parse_path(&attribute("d")?.unwrap_or_default()).map_err(|err| format!("<path> d: {}", err))?,
+        "polygon" | "polyline" => {
+            let numbers = Lexer::new(&attribute("points")?.unwrap_or_default()).numbers().map_err(|err| format!("<{}> points: {}", name, err))?;
+            vec![numbers.chunks_exact(2).map(|pair| Point::new(pair[0], pair[1])).collect()]
+        }
+        "line" => vec![vec![Point::new(number("x1")?, number("y1")?), Point::new(number("x2")?, number("y2")?)]],
+        "rect" => {
+            let (x, y, width, height) = (number("x")?, number("y")?, number("width")?, number("height")?);
+            vec![vec![Point::new(x, y), Point::new(x + width, y), Point::new(x + width, y + height), Point::new(x, y + height)]]
+        }
+        "circle" => {
+            let r = number("r")?;
+            vec![ellipse(number("cx")?, number("cy")?, r, r)]
+        }
+        "ellipse" => vec![ellipse(number("cx")?, number("cy")?, number("rx")?, number("ry")?)],
+        _ => Vec::new(),
+    };
+
+    // HVF paths are closed already, and need at least two points to be drawn
+    Ok(paths.into_iter().map(close).filter(|path| path.len() > 1).collect())
+}
+
+fn close(mut path: Points) -> Points {
+    if path.len() > 1 && path.first() == path.last() {
+        path.pop();
+    }
+    path
+}
+
+fn ellipse(cx: f64, cy: f64, rx: f64, ry: f64) -> Points {
+    (0..ELLIPSE_POINTS)
+        .map(|i| {
+            let angle = std::f64::consts::TAU * i as f64 / ELLIPSE_POINTS as f64;
+            Point::new(cx + rx * angle.cos(), cy + ry * angle.sin())
+        })
+        .collect()
+}
+
+// Path data, e.g. "M 10 10 L 90 10 Q 90 90 10 90 Z". Every subpath becomes a path.
+fn parse_path(d: &str) -> Result<Vec<Points>, String> {
+    let mut lexer = Lexer::new(d);
+    let mut paths = Vec::new();
+    let mut path: Points = Vec::new();
+    let (mut x, mut y) = (0.0, 0.0);
+    let (mut start_x, mut start_y) = (0.0, 0.0);
+    let mut control: Option<(char, f64, f64)> = None;     // Last control point, for S and T
+    let mut command = None;
+
+    while lexer.skip_separators() {
+        command = match lexer.command() {
+            Some(next) => Some(next),
+            // Numbers without a command repeat the last one, coordinates after a moveto are linetos
+            None => match command {
+                Some('M') => Some('L'),
+                Some('m') => Some('l'),
+                Some('Z' | 'z') | None => return Err(format!("Expected a command at {}", lexer.position)),
+                other => other,
+            },
+        };
+        let command = command.unwrap();
+        let relative = command.is_ascii_lowercase();
+        let (ox, oy) = if relative { (x, y) } else { (0.0, 0.0) };
+
+        // Drawing on after a Z starts a new path where the last one started
+        if path.is_empty() && !matches!(command, 'M' | 'm' | 'Z' | 'z') {
+            path.push(Point::new(x, y));
+        }
+
+        match command.to_ascii_uppercase() {
+            'M' => {
+                paths.push(std::mem::take(&mut path));
+                (x, y) = (ox + lexer.number()?, oy + lexer.number()?);
+                (start_x, start_y) = (x, y);
+                path.push(Point::new(x, y));
+            }
+            'L' => {
+                (x, y) = (ox + lexer.number()?, oy + lexer.number()?);
+                path.push(Point::new(x, y));
+            }
+            'H' => {
+                x = ox + lexer.number()?;
+                path.push(Point::new(x, y));
+            }
+            'V' => {
+                y = oy + lexer.number()?;
+                path.push(Point::new(x, y));
+            }
+            'C' | 'S' => {
+                let (x1, y1) = match command.to_ascii_uppercase() {
+                    'C' => (ox + lexer.number()?, oy + lexer.number()?),
+                    _ => match control {
+                        Some(('C' | 'S', cx, cy)) => (2.0 * x - cx, 2.0 * y - cy),
+                        _ => (x, y),
+                    },
+                };
+                let (x2, y2) = (ox + lexer.number()?, oy + lexer.number()?);
+                let (x3, y3) = (ox + lexer.number()?, oy + lexer.number()?);
+                for step in 1..=CURVE_STEPS {
+                    let t = step as f64 / CURVE_STEPS as f64;
+                    let u = 1.0 - t;
+                    path.push(Point::new(
+                        u * u * u * x + 3.0 * u * u * t * x1 + 3.0 * u * t * t * x2 + t * t * t * x3,
+                        u * u * u * y + 3.0 * u * u * t * y1 + 3.0 * u * t * t * y2 + t * t * t * y3,
+                    ));
+                }
+                control = Some((command.to_ascii_uppercase(), x2, y2));
+                (x, y) = (x3, y3);
+                continue;
+            }
+            'Q' | 'T' => {
+                let (x1, y1) = match command.to_ascii_uppercase() {
+                    'Q' => (ox + lexer.number()?, oy + lexer.number()?),
+                    _ => match control {
+                        Some(('Q' | 'T', cx, cy)) => (2.0 * x - cx, 2.0 * y - cy),
+                        _ => (x, y),
+                    },
+                };
+                let (x2, y2) = (ox + lexer.number()?, oy + lexer.number()?);
+                for step in 1..=CURVE_STEPS {
+                    let t = step as f64 / CURVE_STEPS as f64;
+                    let u = 1.0 - t;
+                    path.push(Point::new(u * u * x + 2.0 * u * t * x1 + t * t * x2, u * u * y + 2.0 * u * t * y1 + t * t * y2));
+                }
+                control = Some((command.to_ascii_uppercase(), x1, y1));
+                (x, y) = (x2, y2);
+                continue;
+            }
+            'Z' => {
+                paths.push(std::mem::take(&mut path));
+                (x, y) = (start_x, start_y);
+            }
+            'A' => return Err("Arcs aren't supported, convert them to curves".to_string()),
+            other => return Err(format!("Unknown command {}", other)),
+        }
+        control = None;
+    }
+
+    paths.push(path);
+    Ok(paths.into_iter().filter(|path| !path.is_empty()).collect())
+}
+
+// Commands and numbers of path data and point lists, separated by whitespace and commas.
+struct Lexer<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(data: &'a str) -> Self {
+        Self { data: data.as_bytes(), position: 0 }
+    }
+
+    // Skip to the next token, false at the end.
+    fn skip_separators(&mut self) -> bool {
+        while self.position < self.data.len() && (self.data[self.position].is_ascii_whitespace() || self.data[self.position] == b',') {
+            self.position += 1;
+        }
+        self.position < self.data.len()
+    }
+
+    fn command(&mut self) -> Option<char> {
+        let byte = *self.data.get(self.position)?;
+        // e and E only appear in exponents
+        if byte.is_ascii_alphabetic() && !matches!(byte, b'e' | b'E') {
+            self.position += 1;
+            return Some(byte as char);
+        }
+        None
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        self.skip_separators();
+        let start = self.position;
+        let mut seen_dot = false;
+        let mut seen_exponent = false;
+
+        while let Some(&byte) = self.data.get(self.position) {
+            let at_start = self.position == start || matches!(self.data[self.position - 1], b'e' | b'E');
+            match byte {
+                b'+' | b'-' if at_start => {}
+                b'0'..=b'9' => {}
+                // "0.5.5" is two numbers
+                b'.' if !seen_dot && !seen_exponent => seen_dot = true,
+                b'e' | b'E' if !seen_exponent && self.position > start => seen_exponent = true,
+                _ => break,
+            }
+            self.position += 1;
+        }
+
+        let token = std::str::from_utf8(&self.data[start..self.position]).unwrap_or_default();
+        token.parse().map_err(|_| format!("Expected a number at {}", start))
+    }
+
+    fn numbers(&mut self) -> Result<Vec<f64>, String> {
+        let mut numbers = Vec::new();
+        while self.skip_separators() {
+            numbers.push(self.number()?);
+        }
+        Ok(numbers)
+    }
+}
@@ -1,8 +1,8 @@
 use smithay_client_toolkit::{
-    delegate_pointer, delegate_seat, seat::{pointer::{PointerEventKind, PointerHandler}, Capability, SeatHandler, SeatState}, shell::WaylandSurface};
+    delegate_pointer, delegate_seat, seat::{pointer::{PointerEventKind, PointerHandler}, Capability, SeatHandler, SeatState}};
 use wayland_client::{protocol::wl_pointer, QueueHandle};
 
-use crate::layer::HyogenLayer;
+use crate::{events::Event, layer::HyogenLayer};
 
 impl SeatHandler for HyogenLayer {
     fn seat_state(&mut self) -> &mut SeatState {
@@ -48,27 +48,40 @@ impl PointerHandler for HyogenLayer {
     fn pointer_frame(
             &mut self,
             _conn: &wayland_client::Connection,
-            _qh: &QueueHandle<Self>,
+            qh: &QueueHandle<Self>,
             _pointer: &wl_pointer::WlPointer,
             events: &[smithay_client_toolkit::seat::pointer::PointerEvent],
         ) {
         use PointerEventKind::*;
 
+        let mut woke = false;
         for event in events {
             // Ignore events for other surfaces
-            if &event.surface != self.layer.wl_surface() {
+            let Some(surface) = self.surface_index(&event.surface) else {
                 continue;
-            }
+            };
+            woke = true;
             
             match event.kind {
-                Enter { .. } => {}
-                Leave { .. } => {}
+                Enter { .. } => self.face.publish(Event::PointerEntered { surface }),
+                Leave { .. } => self.face.publish(Event::PointerLeft { surface }),
                 Motion { .. } => {}
-                Press { .. } => {}
+                Press { .. } => {
+                    let (x, y) = event.position;
+                    if let Some(region) = self.hit_test(&event.surface, x, y) {
+                        tracing::debug!("Pressed face region {}", region);
+                        self.face.publish(Event::Tapped { region });
+                    }
+                }
                 Release { .. } => {}
                 Axis { .. } => {}
             }
         }
+
+        // Input wakes an idle face.
+        if woke {
+            self.request_redraw(qh);
+        }
     }
 }
 delegate_pointer!(HyogenLayer);
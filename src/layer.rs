@@ -1,29 +1,39 @@
 use std::time::{Duration, Instant};
 
 use smithay_client_toolkit::{
-    compositor::CompositorHandler, delegate_compositor, delegate_layer, delegate_output, delegate_registry, delegate_shm, output::{OutputHandler, OutputState}, registry::{ProvidesRegistryState, RegistryState}, registry_handlers, seat::SeatState, shell::{wlr_layer::{LayerShellHandler, LayerSurface}, WaylandSurface}, shm::{slot::SlotPool, Shm, ShmHandler}
+    compositor::{CompositorHandler, CompositorState}, delegate_compositor, delegate_layer, delegate_output, delegate_registry, delegate_shm, output::{OutputHandler, OutputState}, registry::{ProvidesRegistryState, RegistryState}, registry_handlers, seat::SeatState, shell::{wlr_layer::{LayerShellHandler, LayerSurface}, WaylandSurface}, shm::{Shm, ShmHandler}
 };
-use wayland_client::{globals::GlobalList, protocol::{wl_pointer, wl_shm}, QueueHandle};
+use serde_json::Value;
+use wayland_client::{globals::GlobalList, protocol::{wl_output::WlOutput, wl_pointer, wl_surface::WlSurface}, QueueHandle};
 
-use crate::{animator::animator::Animator, hvf::hvf::HVF};
+use crate::{animator::animator::Animator, compositor_control::{CompositorControl, CompositorControlHandler}, delegate_hyogen_control, control::{protocol::Command, ControlHandler}, events::{Event, EventBus}, face::FaceState, geometry::Shape, hvf::hvf::HVF, layout::{self, Layout, Transform}, renderer::{Frame, Renderer}, placement::{OutputSelector, Placement}, scale::ScaleManagers, surface::{FaceSurface, Shell}};
+
+// How often to check back while an animation holds still without being settled.
+const HOLD_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// The farewell animation played before exiting, and how long past it we wait for it to be presented.
+const FAREWELL_DURATION: Duration = Duration::from_millis(400);
+const FAREWELL_GRACE: Duration = Duration::from_millis(500);
 
 pub struct HyogenLayer {
     registry_state: RegistryState,
     pub seat_state: SeatState,
     output_state: OutputState,
+    compositor: CompositorState,
+    shell: Shell,
     shm: Shm,
-    pool: SlotPool,
+    scale_managers: ScaleManagers,
+    qh: QueueHandle<HyogenLayer>,       // For requests made outside of Wayland dispatch, e.g. control commands
+    compositor_control: CompositorControl,
 
-    pub layer: LayerSurface,
+    pub surfaces: Vec<FaceSurface>,     // One per output the face is shown on
     pub pointer: Option<wl_pointer::WlPointer>,
+    frame: Frame,                       // Refilled for every frame drawn, so drawing doesn't allocate
 
-    width: u32,
-    height: u32,
-    first_configure: bool,
     exit: bool,
+    exiting: Option<Instant>,           // Saying goodbye, exits once done or at this deadline
 
-    // hvf_loader: HVFLoader,
-    pub animator: Animator
+    pub face: FaceState,                // Everything that survives a reconnect
 }
 
 impl OutputHandler for HyogenLayer {
@@ -34,57 +44,72 @@ impl OutputHandler for HyogenLayer {
     fn new_output(
             &mut self,
             _conn: &wayland_client::Connection,
-            _qh: &QueueHandle<Self>,
-            _output: wayland_client::protocol::wl_output::WlOutput,
+            qh: &QueueHandle<Self>,
+            output: wayland_client::protocol::wl_output::WlOutput,
         ) {
-        
+        let name = self.output_name(&output);
+        self.face.publish(Event::OutputAdded { name });
+
+        // Outputs can be plugged in at any time, give it a face if it's one we want
+        self.sync_surfaces(qh);
     }
 
     fn update_output(
             &mut self,
             _conn: &wayland_client::Connection,
-            _qh: &QueueHandle<Self>,
+            qh: &QueueHandle<Self>,
             _output: wayland_client::protocol::wl_output::WlOutput,
         ) {
-        
+        // The name and description may only be known now, and the scale could have changed
+        self.sync_surfaces(qh);
+        for index in 0..self.surfaces.len() {
+            self.output_scale_changed(index, qh);
+        }
     }
 
     fn output_destroyed(
             &mut self,
             _conn: &wayland_client::Connection,
             _qh: &QueueHandle<Self>,
-            _output: wayland_client::protocol::wl_output::WlOutput,
+            output: wayland_client::protocol::wl_output::WlOutput,
         ) {
-        
+        // The output is still listed by the output state at this point, so drop its face here
+        let before = self.surfaces.len();
+        self.surfaces.retain(|surface| surface.output.as_ref() != Some(&output));
+        if self.surfaces.len() != before {
+            tracing::info!("Output {} is gone, removed its face", self.output_name(&output));
+        }
+
+        for surface in &mut self.surfaces {
+            surface.scale.leave(&output);
+        }
+
+        let name = self.output_name(&output);
+        self.face.publish(Event::OutputRemoved { name });
     }
 }
 delegate_output!(HyogenLayer);
 
 impl LayerShellHandler for HyogenLayer {
-    fn closed(&mut self, _conn: &wayland_client::Connection, _qh: &QueueHandle<Self>, _layer: &LayerSurface) {
-        self.exit = true;
+    fn closed(&mut self, _conn: &wayland_client::Connection, _qh: &QueueHandle<Self>, layer: &LayerSurface) {
+        self.surfaces.retain(|surface| surface.wl_surface() != layer.wl_surface());
+
+        // With a chosen set of outputs the face comes back when an output is plugged in again
+        if self.face.placement.outputs == OutputSelector::Auto {
+            self.exit = true;
+        }
     }
 
     fn configure(
             &mut self,
             _conn: &wayland_client::Connection,
             qh: &QueueHandle<Self>,
-            _layer: &LayerSurface,
+            layer: &LayerSurface,
             configure: smithay_client_toolkit::shell::wlr_layer::LayerSurfaceConfigure,
             _serial: u32,
         ) {
-        if configure.new_size.0 == 0 || configure.new_size.1 == 0 {
-            self.width = 800;
-            self.height = 480;
-        } else {
-            self.width = configure.new_size.0;
-            self.height = configure.new_size.1;
-        }
-
-        // Initiate the first draw.
-        if self.first_configure {
-            self.first_configure = false;
-            self.draw(qh);
+        if let Some(index) = self.surface_index(layer.wl_surface()) {
+            self.configure_surface(index, configure.new_size, qh);
         }
     }
 }
@@ -94,10 +119,16 @@ impl CompositorHandler for HyogenLayer {
     fn scale_factor_changed(
             &mut self,
             _conn: &wayland_client::Connection,
-            _qh: &QueueHandle<Self>,
-            _surface: &wayland_client::protocol::wl_surface::WlSurface,
-            _new_factor: i32,
-        ) { }
+            qh: &QueueHandle<Self>,
+            surface: &wayland_client::protocol::wl_surface::WlSurface,
+            new_factor: i32,
+        ) {
+        if let Some(index) = self.surface_index(surface) {
+            if self.surfaces[index].scale.set_integer_scale(new_factor) {
+                self.scale_changed(index, qh);
+            }
+        }
+    }
 
     fn transform_changed(
             &mut self,
@@ -110,27 +141,42 @@ impl CompositorHandler for HyogenLayer {
     fn surface_enter(
             &mut self,
             _conn: &wayland_client::Connection,
-            _qh: &QueueHandle<Self>,
-            _surface: &wayland_client::protocol::wl_surface::WlSurface,
-            _output: &wayland_client::protocol::wl_output::WlOutput,
-        ) { }
+            qh: &QueueHandle<Self>,
+            surface: &wayland_client::protocol::wl_surface::WlSurface,
+            output: &wayland_client::protocol::wl_output::WlOutput,
+        ) {
+        if let Some(index) = self.surface_index(surface) {
+            self.surfaces[index].scale.enter(output);
+            self.output_scale_changed(index, qh);
+        }
+    }
 
     fn surface_leave(
             &mut self,
             _conn: &wayland_client::Connection,
-            _qh: &QueueHandle<Self>,
-            _surface: &wayland_client::protocol::wl_surface::WlSurface,
-            _output: &wayland_client::protocol::wl_output::WlOutput,
-        ) { }
+            qh: &QueueHandle<Self>,
+            surface: &wayland_client::protocol::wl_surface::WlSurface,
+            output: &wayland_client::protocol::wl_output::WlOutput,
+        ) {
+        if let Some(index) = self.surface_index(surface) {
+            self.surfaces[index].scale.leave(output);
+            self.output_scale_changed(index, qh);
+        }
+    }
 
     fn frame(
             &mut self,
             _conn: &wayland_client::Connection,
             qh: &QueueHandle<Self>,
-            _surface: &wayland_client::protocol::wl_surface::WlSurface,
+            surface: &wayland_client::protocol::wl_surface::WlSurface,
             _time: u32,
         ) {
-        self.draw(qh);
+        // Drawn along with every other face waiting for its next frame, so the fps cap is shared fairly
+        // between outputs instead of each callback using it up for the others
+        if let Some(index) = self.surface_index(surface) {
+            self.surfaces[index].frame_pending = false;
+            self.request_redraw(qh);
+        }
     }
 }
 delegate_compositor!(HyogenLayer);
@@ -151,30 +197,175 @@ impl ProvidesRegistryState for HyogenLayer {
 }
 delegate_registry!(HyogenLayer);
 
+impl ControlHandler for HyogenLayer {
+    fn handle_command(&mut self, command: Command) -> Result<Value, String> {
+        if self.exiting.is_some() {
+            return Err("Hyogen UI is exiting".to_string());
+        }
+
+        let body = self.face.apply(command, Instant::now())?;
+        let qh = self.qh.clone();
+        self.request_redraw(&qh);
+        Ok(body)
+    }
+
+    fn events(&mut self) -> &mut EventBus {
+        &mut self.face.events
+    }
+}
+
+impl CompositorControlHandler for HyogenLayer {
+    fn compositor_control(&mut self) -> &mut CompositorControl {
+        &mut self.compositor_control
+    }
+
+    fn animator(&self) -> &Animator {
+        &self.face.animator
+    }
+}
+delegate_hyogen_control!(HyogenLayer);
+
 impl HyogenLayer {
-    pub fn new(layer: LayerSurface, globals: &GlobalList, qh: &QueueHandle<HyogenLayer>, shm: Shm, pool: SlotPool, default_ring: &HVF) -> Self {
-        // let default_ring = hvf_loader.get("expression", "neutral").unwrap();
-        let mut animator = Animator::new(default_ring);
-        animator.animate(default_ring, Duration::from_secs_f32(0.0));
-        // animator.animate(to_ring);
-        
-        HyogenLayer {
+    pub fn new(compositor: CompositorState, shell: Shell, placement: Placement, globals: &GlobalList, qh: &QueueHandle<HyogenLayer>, shm: Shm, default_ring: &HVF) -> Self {
+        Self::with_face(compositor, shell, globals, qh, shm, FaceState::new(default_ring, placement))
+    }
+
+    // Put an existing face on a (new) connection, e.g. after the compositor restarted.
+    pub fn with_face(compositor: CompositorState, shell: Shell, globals: &GlobalList, qh: &QueueHandle<HyogenLayer>, shm: Shm, face: FaceState) -> Self {
+        let mut hyogen_layer = HyogenLayer {
             registry_state: RegistryState::new(globals),
             seat_state: SeatState::new(globals, qh),
             output_state: OutputState::new(globals, qh),
+            compositor,
+            shell,
             shm,
-            pool,
+            scale_managers: ScaleManagers::bind(globals, qh),
+            qh: qh.clone(),
+            compositor_control: CompositorControl::bind(globals, qh),
 
-            layer,
+            surfaces: Vec::new(),
             pointer: None,
-            
-            width: 800,
-            height: 480,
-            first_configure: true,
+            frame: Frame::new(Shape::new(), Vec::new(), face.background),
+
             exit: false,
+            exiting: None,
+
+            face,
+        };
+
+        // Chosen outputs get their faces once they're announced
+        hyogen_layer.sync_surfaces(qh);
+        hyogen_layer
+    }
+
+    // Everything that outlives the connection, the Wayland objects are dropped.
+    pub fn into_face_state(self) -> FaceState {
+        self.face
+    }
+
+    fn output_name(&self, output: &WlOutput) -> String {
+        self.output_state.info(output)
+            .and_then(|info| info.name)
+            .unwrap_or_else(|| "<unnamed>".to_string())
+    }
+
+    // Index of the face shown on the given wl_surface.
+    pub fn surface_index(&self, surface: &WlSurface) -> Option<usize> {
+        self.surfaces.iter().position(|face| face.wl_surface() == surface)
+    }
+
+    // Create and destroy faces so there's exactly one on every output the placement selects.
+    fn sync_surfaces(&mut self, qh: &QueueHandle<Self>) {
+        // Windows can't be put on an output, there's just one
+        let wanted: Vec<Option<WlOutput>> = match &self.face.placement.outputs {
+            _ if matches!(self.shell, Shell::Xdg(_)) => vec![None],
+            OutputSelector::Auto => vec![None],
+            selector => self.output_state.outputs()
+                .filter(|output| self.output_state.info(output).is_some_and(|info| selector.matches(&info)))
+                .map(Some)
+                .collect(),
+        };
+
+        self.surfaces.retain(|surface| wanted.contains(&surface.output));
+
+        for output in wanted {
+            if self.surfaces.iter().any(|surface| surface.output == output) {
+                continue;
+            }
+
+            match FaceSurface::new(&self.compositor, &self.shell, &self.face.placement, output.clone(), &self.scale_managers, &self.shm, qh) {
+                Ok(surface) => {
+                    if let Some(output) = &output {
+                        tracing::info!("Showing the face on output {}", self.output_name(output));
+                    }
+                    self.surfaces.push(surface);
+                }
+                Err(err) => tracing::error!("Failed to create a surface: {}", err),
+            }
+        }
+
+        if self.surfaces.is_empty() {
+            tracing::debug!("No output matches {:?} yet", self.face.placement.outputs);
+        }
+    }
 
-            // hvf_loader,
-            animator
+    // Move the face to a new placement, recreating surfaces only when the protocol requires it.
+    pub fn set_placement(&mut self, placement: Placement, qh: &QueueHandle<Self>) -> Result<(), String> {
+        placement.validate().map_err(|(key, message)| format!("{}: {}", key, message))?;
+        if placement == self.face.placement {
+            return Ok(());
+        }
+
+        // The namespace is fixed at creation and changing layers needs zwlr_layer_surface_v1 version 2
+        let layer_changed = placement.layer != self.face.placement.layer;
+        let recreate = placement.namespace != self.face.placement.namespace
+            || (layer_changed && !self.surfaces.iter().all(FaceSurface::can_change_layer));
+
+        self.face.placement = placement;
+        if recreate {
+            tracing::info!("Recreating the layer surfaces for the new placement");
+            self.surfaces.clear();
+        } else {
+            for surface in &self.surfaces {
+                surface.apply_placement(&self.face.placement, layer_changed);
+            }
+        }
+
+        // The set of outputs may have changed too
+        self.sync_surfaces(qh);
+        Ok(())
+    }
+
+    // The compositor told a face what size to be, a 0 leaves it up to us.
+    pub fn configure_surface(&mut self, index: usize, new_size: (u32, u32), qh: &QueueHandle<Self>) {
+        let surface = &mut self.surfaces[index];
+
+        // The compositor leaves the size to us if it has no preference, fall back to the requested size
+        let (width, height) = if new_size.0 == 0 || new_size.1 == 0 {
+            surface.fallback_size(&self.face.placement, &self.output_state)
+        } else {
+            new_size
+        };
+        let resized = (width, height) != (surface.width, surface.height);
+
+        surface.width = width;
+        surface.height = height;
+
+        let (buffer_width, buffer_height) = surface.scale.buffer_size(width, height);
+        surface.backend.resize(buffer_width, buffer_height);
+        let first_configure = surface.first_configure;
+
+        if first_configure || resized {
+            self.face.publish(Event::Configured { surface: index, width, height });
+        }
+        self.surfaces[index].first_configure = false;
+
+        // Every configure is answered with a commit, even one that keeps the size. When the fps cap or an unchanged
+        // frame skips drawing, the buffer already attached is committed again.
+        if !self.draw(index, qh) {
+            let surface = &self.surfaces[index];
+            surface.scale.apply(surface.wl_surface(), surface.width, surface.height);
+            surface.commit();
         }
     }
 
@@ -182,41 +373,190 @@ impl HyogenLayer {
         self.exit
     }
 
-    pub fn draw(&mut self, qh: &QueueHandle<Self>) {
-        let width = self.width;
-        let height = self.height;
-        let stride = self.width as i32 * 4;
-    
-        let (buffer, canvas) = self.pool
-            .create_buffer(width as i32, height as i32, stride, wl_shm::Format::Argb8888)
-            .expect("create buffer");
-    
-        // Clear the canvas
-        canvas.fill(0);
-
-        for path in &self.animator.get_path(Instant::now()) {
-            for coordinated in path {
-                if coordinated.len() == 2 {
-                    let x = ((coordinated[0] / 800.0) * width as f64) as usize; // Assuming original width = 800
-                    let y = ((coordinated[1] / 480.0) * height as f64) as usize; // Assuming original height = 600
-
-                    if x < width as usize && y < height as usize {
-                        let index = (y * width as usize + x) * 4;
-
-                        // Set color (e.g., white)
-                        canvas[index] = 0xFF; // Blue
-                        canvas[index + 1] = 0xFF; // Green
-                        canvas[index + 2] = 0xFF; // Red
-                        canvas[index + 3] = 0xFF; // Alpha
-                    }    
-                }
+    pub fn exiting(&self) -> bool {
+        self.exit || self.exiting.is_some()
+    }
+
+    // Play the farewell animation and exit once it's done, asking twice exits right away.
+    pub fn begin_exit(&mut self, qh: &QueueHandle<Self>) {
+        if !self.exiting() {
+            self.face.publish(Event::Exiting);
+        }
+
+        // The expression may have changed since the farewell was picked, exit right away if it can't morph
+        let farewell = match &self.face.farewell {
+            Some(farewell) if self.exiting.is_none() && !self.surfaces.is_empty()
+                && self.face.animator.target_ring().morphs_into(farewell) => farewell.clone(),
+            _ => {
+                self.exit = true;
+                return;
+            }
+        };
+
+        let now = Instant::now();
+        self.face.blink = None;
+        self.face.animator.resume(now);
+        self.face.animator.animate_at(&farewell, FAREWELL_DURATION, now);
+        self.exiting = Some(now + FAREWELL_DURATION + FAREWELL_GRACE);
+        self.request_redraw(qh);
+    }
+
+    // Re-read the settings and the HVF file, see `FaceState::reload`.
+    pub fn reload(&mut self, qh: &QueueHandle<Self>) {
+        self.face.reload(Instant::now());
+
+        let placement = self.face.config.placement.clone();
+        if let Err(err) = self.set_placement(placement, qh) {
+            tracing::error!("Keeping the current placement: {}", err);
+        }
+        self.request_redraw(qh);
+    }
+
+    pub fn set_renderer(&mut self, renderer: Box<dyn Renderer>) {
+        self.face.renderer = renderer;
+    }
+
+    // Re-render a face at its new physical resolution.
+    pub fn scale_changed(&mut self, index: usize, qh: &QueueHandle<Self>) {
+        let surface = &mut self.surfaces[index];
+        tracing::info!("Rendering at scale {}", surface.scale.factor());
+
+        let (buffer_width, buffer_height) = surface.scale.buffer_size(surface.width, surface.height);
+        surface.backend.resize(buffer_width, buffer_height);
+        if !surface.first_configure {
+            self.draw(index, qh);
+        }
+    }
+
+    pub fn fractional_scale_changed(&mut self, surface: &WlSurface, scale: u32, qh: &QueueHandle<Self>) {
+        if let Some(index) = self.surface_index(surface) {
+            if self.surfaces[index].scale.set_fractional_scale(scale) {
+                self.scale_changed(index, qh);
+            }
+        }
+    }
+
+    fn output_scale_changed(&mut self, index: usize, qh: &QueueHandle<Self>) {
+        let surface = &mut self.surfaces[index];
+        if let Some(scale) = surface.scale.preferred_output_scale(&self.output_state) {
+            if surface.scale.set_integer_scale(scale) {
+                self.scale_changed(index, qh);
             }
         }
-    
-        self.layer.wl_surface().damage_buffer(0, 0, width as i32, height as i32);
-        self.layer.wl_surface().frame(qh, self.layer.wl_surface().clone());
-    
-        buffer.attach_to(self.layer.wl_surface()).expect("buffer attach");
-        self.layer.commit();
-    }   
-}
\ No newline at end of file
+    }
+
+    pub fn set_layout(&mut self, layout: Layout, qh: &QueueHandle<Self>) {
+        self.face.layout = layout;
+        self.request_redraw(qh);
+    }
+
+    // Design space transform in surface-local coordinates of a face, as used by pointer and touch events.
+    pub fn transform(&self, index: usize) -> Transform {
+        let surface = &self.surfaces[index];
+        self.face.transform(surface.width, surface.height, 1.0, Instant::now())
+    }
+
+    // Index of the face path under a surface-local position. Tested against the last frame drawn, which is what
+    // the pointer is over, sampling the animator would also advance it.
+    pub fn hit_test(&self, surface: &WlSurface, x: f64, y: f64) -> Option<usize> {
+        let index = self.surface_index(surface)?;
+        let (x, y) = self.transform(index).to_design(x, y);
+        layout::hit_test(&self.frame.paths, x, y)
+    }
+
+    pub fn set_fps_cap(&mut self, fps_cap: Option<f64>) {
+        self.face.scheduler.set_fps_cap(fps_cap);
+    }
+
+    // Earliest time `tick` has work to do, None if only Wayland events can wake us.
+    pub fn next_wakeup(&self) -> Option<Instant> {
+        let face = self.face.next_wakeup();
+        let compositor_control = self.compositor_control.next_wakeup(&self.face.animator);
+
+        // While exiting, wake up when the farewell is done even if no frame callback arrives
+        let farewell = self.exiting.map(|deadline| self.face.animator.settles_at().map_or(deadline, |at| at.min(deadline)));
+        self.face.scheduler.next_wakeup().into_iter().chain(face).chain(compositor_control).chain(farewell).min()
+    }
+
+    // Run timers that expired, starting to draw again if any of them needs it.
+    pub fn tick(&mut self, qh: &QueueHandle<Self>) {
+        let now = Instant::now();
+        let mut wake = self.face.scheduler.take_due(now);
+        wake |= self.face.poll(now);
+        self.compositor_control.poll(&self.face.animator, now);
+
+        if wake {
+            self.request_redraw(qh);
+        }
+
+        if self.exiting.is_some_and(|deadline| now >= deadline || self.face.animator.is_settled(now)) {
+            // Present the end of the farewell without waiting for frame callbacks
+            for index in 0..self.surfaces.len() {
+                self.draw_surface(index, now, qh);
+            }
+            self.exit = true;
+        }
+    }
+
+    // Draw every face that isn't waiting for a frame callback, e.g. to restart drawing after it went idle
+    // when a new animation was started.
+    pub fn request_redraw(&mut self, qh: &QueueHandle<Self>) {
+        let idle: Vec<usize> = (0..self.surfaces.len())
+            .filter(|index| !self.surfaces[*index].frame_pending && !self.surfaces[*index].first_configure)
+            .collect();
+        if idle.is_empty() {
+            return;
+        }
+
+        // All idle faces are drawn as one frame as far as the fps cap is concerned
+        let now = Instant::now();
+        if !self.face.scheduler.begin_frame(now) {
+            return;
+        }
+        for index in idle {
+            self.draw_surface(index, now, qh);
+        }
+    }
+
+    // Draw one face right away, when it has a new size or scale. Returns whether a frame was committed.
+    pub fn draw(&mut self, index: usize, qh: &QueueHandle<Self>) -> bool {
+        let now = Instant::now();
+
+        // Over the fps cap, the scheduler wakes us once the frame is allowed.
+        if !self.face.scheduler.begin_frame(now) {
+            return false;
+        }
+        self.draw_surface(index, now, qh)
+    }
+
+    fn draw_surface(&mut self, index: usize, now: Instant, qh: &QueueHandle<Self>) -> bool {
+        let surface = &mut self.surfaces[index];
+
+        let (buffer_width, buffer_height) = surface.scale.buffer_size(surface.width, surface.height);
+        let frame = &mut self.frame;
+        self.face.animator.get_path_into(now, &mut frame.paths);
+        frame.styles.clear();
+        frame.styles.push(self.face.style);
+        frame.background = self.face.background;
+        frame.transform = self.face.transform(buffer_width, buffer_height, surface.scale.factor(), now);
+        let settled = self.face.is_settled(now);
+
+        // Nothing changed, skip the commit. Keep checking back if the animation is only holding still.
+        if !surface.backend.draw(self.face.renderer.as_mut(), frame, surface.role.wl_surface(), buffer_width, buffer_height) {
+            if !settled {
+                self.face.scheduler.schedule(now + HOLD_POLL_INTERVAL);
+            }
+            return false;
+        }
+
+        surface.scale.apply(surface.wl_surface(), surface.width, surface.height);
+
+        // Once everything settled this is the last frame, stop requesting callbacks until woken up again.
+        if !settled && !surface.frame_pending {
+            surface.wl_surface().frame(qh, surface.wl_surface().clone());
+            surface.frame_pending = true;
+        }
+        surface.commit();
+        true
+    }
+}
@@ -0,0 +1,212 @@
+use std::str::FromStr;
+
+use crate::{geometry::Shape, renderer::{DESIGN_HEIGHT, DESIGN_WIDTH}};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutMode {
+    Contain,        // Fit the whole design, keeping its aspect ratio
+    Cover,          // Fill the surface, keeping the aspect ratio and cropping overflow
+    Stretch,        // Scale each axis independently
+    Fixed(f64),     // Surface pixels per design unit
+}
+
+impl FromStr for LayoutMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "contain" => Ok(LayoutMode::Contain),
+            "cover" => Ok(LayoutMode::Cover),
+            "stretch" => Ok(LayoutMode::Stretch),
+            _ => {
+                // fixed or fixed:<scale>
+                let scale = match value.strip_prefix("fixed") {
+                    Some("") => 1.0,
+                    Some(scale) => scale.strip_prefix(':')
+                        .and_then(|scale| scale.parse::<f64>().ok())
+                        .filter(|scale| *scale > 0.0 && scale.is_finite())
+                        .ok_or_else(|| format!("Invalid fixed scale in {}", value))?,
+                    None => return Err(format!("Unknown layout mode {}, expected contain, cover, stretch or fixed:<scale>", value)),
+                };
+                Ok(LayoutMode::Fixed(scale))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+}
+
+impl Align {
+    fn offset(self, free: f64) -> f64 {
+        match self {
+            Align::Start => 0.0,
+            Align::Center => free / 2.0,
+            Align::End => free,
+        }
+    }
+}
+
+// Where the design sits inside the surface when there is space left over (or overflow).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    pub horizontal: Align,
+    pub vertical: Align,
+}
+
+impl Alignment {
+    pub const CENTER: Alignment = Alignment { horizontal: Align::Center, vertical: Align::Center };
+}
+
+impl FromStr for Alignment {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (vertical, horizontal) = match value {
+            "center" => (Align::Center, Align::Center),
+            "top" => (Align::Start, Align::Center),
+            "bottom" => (Align::End, Align::Center),
+            "left" => (Align::Center, Align::Start),
+            "right" => (Align::Center, Align::End),
+            "top-left" => (Align::Start, Align::Start),
+            "top-right" => (Align::Start, Align::End),
+            "bottom-left" => (Align::End, Align::Start),
+            "bottom-right" => (Align::End, Align::End),
+            _ => return Err(format!("Unknown alignment {}", value)),
+        };
+        Ok(Alignment { horizontal, vertical })
+    }
+}
+
+// Space kept free around the design, in surface-local (logical) pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Padding {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl Padding {
+    pub fn uniform(padding: f64) -> Self {
+        Self { top: padding, right: padding, bottom: padding, left: padding }
+    }
+}
+
+impl FromStr for Padding {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        // CSS style: `all`, `vertical,horizontal` or `top,right,bottom,left`
+        let values = value
+            .split(',')
+            .map(|part| part.trim().parse::<f64>().ok().filter(|v| *v >= 0.0 && v.is_finite()))
+            .collect::<Option<Vec<f64>>>()
+            .ok_or_else(|| format!("Invalid padding {}", value))?;
+
+        match values[..] {
+            [all] => Ok(Padding::uniform(all)),
+            [vertical, horizontal] => Ok(Padding { top: vertical, right: horizontal, bottom: vertical, left: horizontal }),
+            [top, right, bottom, left] => Ok(Padding { top, right, bottom, left }),
+            _ => Err(format!("Invalid padding {}, expected 1, 2 or 4 values", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub mode: LayoutMode,
+    pub align: Alignment,
+    pub padding: Padding,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            mode: LayoutMode::Contain,
+            align: Alignment::CENTER,
+            padding: Padding::default(),
+        }
+    }
+}
+
+impl Layout {
+    // Transform for a surface of `width` x `height` pixels, where one logical pixel is `scale` pixels.
+    pub fn transform(&self, width: u32, height: u32, scale: f64) -> Transform {
+        let padding = self.padding;
+        let available_width = (width as f64 - (padding.left + padding.right) * scale).max(0.0);
+        let available_height = (height as f64 - (padding.top + padding.bottom) * scale).max(0.0);
+
+        let fit_x = available_width / DESIGN_WIDTH;
+        let fit_y = available_height / DESIGN_HEIGHT;
+        let (scale_x, scale_y) = match self.mode {
+            LayoutMode::Contain => (fit_x.min(fit_y), fit_x.min(fit_y)),
+            LayoutMode::Cover => (fit_x.max(fit_y), fit_x.max(fit_y)),
+            LayoutMode::Stretch => (fit_x, fit_y),
+            LayoutMode::Fixed(fixed) => (fixed * scale, fixed * scale),
+        };
+
+        let free_x = available_width - DESIGN_WIDTH * scale_x;
+        let free_y = available_height - DESIGN_HEIGHT * scale_y;
+
+        Transform {
+            scale_x,
+            scale_y,
+            offset_x: padding.left * scale + self.align.horizontal.offset(free_x),
+            offset_y: padding.top * scale + self.align.vertical.offset(free_y),
+        }
+    }
+}
+
+// Maps design space onto surface pixels, shared by rendering and input hit-testing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub scale_x: f64,
+    pub scale_y: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self { scale_x: 1.0, scale_y: 1.0, offset_x: 0.0, offset_y: 0.0 }
+    }
+}
+
+impl Transform {
+    pub fn to_surface(&self, x: f64, y: f64) -> (f64, f64) {
+        (x * self.scale_x + self.offset_x, y * self.scale_y + self.offset_y)
+    }
+
+    pub fn to_design(&self, x: f64, y: f64) -> (f64, f64) {
+        ((x - self.offset_x) / self.scale_x, (y - self.offset_y) / self.scale_y)
+    }
+
+    // Factor applied to lengths that don't have a direction, like stroke widths.
+    pub fn length_scale(&self) -> f64 {
+        self.scale_x.min(self.scale_y)
+    }
+}
+
+// Index of the top most path containing the design space point, using the even-odd rule.
+pub fn hit_test(paths: &Shape, x: f64, y: f64) -> Option<usize> {
+    paths.paths().rposition(|path| {
+        let mut inside = false;
+        let mut prev = match path.last() {
+            Some(point) => point,
+            None => return false,
+        };
+
+        for point in path {
+            if (point.y > y) != (prev.y > y) && x < prev.x + (y - prev.y) / (point.y - prev.y) * (point.x - prev.x) {
+                inside = !inside;
+            }
+            prev = point;
+        }
+        inside
+    })
+}
@@ -1,4 +1,41 @@
-pub mod input_handler;
+// Core: the HVF model, animation and software rendering, without any Wayland dependencies
+pub mod geometry;
 pub mod hvf;
 pub mod animator;
-pub mod layer;
\ No newline at end of file
+pub mod events;
+pub mod control;
+pub mod layout;
+pub mod renderer;
+pub mod headless;
+pub mod terminal;
+pub mod scheduler;
+#[cfg(feature = "export")]
+pub mod export;
+
+// Showing the face on a compositor
+#[cfg(feature = "wayland")]
+pub mod input_handler;
+#[cfg(feature = "wayland")]
+pub mod cli;
+#[cfg(feature = "wayland")]
+pub mod config;
+#[cfg(feature = "wayland")]
+pub mod face;
+#[cfg(feature = "wayland")]
+pub mod compositor_control;
+#[cfg(feature = "wayland")]
+pub mod layer;
+#[cfg(feature = "wayland")]
+pub mod window;
+#[cfg(feature = "wayland")]
+pub mod event_loop;
+#[cfg(feature = "wayland")]
+pub mod supervisor;
+#[cfg(feature = "wayland")]
+pub mod surface;
+#[cfg(feature = "wayland")]
+pub mod placement;
+#[cfg(feature = "wayland")]
+pub mod scale;
+#[cfg(feature = "wayland")]
+pub mod watch;
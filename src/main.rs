@@ -1,83 +1,204 @@
-use hyogen_ui::{hvf, layer::HyogenLayer};
-use smithay_client_toolkit::{compositor::CompositorState, shell::{wlr_layer::{Anchor, Layer, LayerShell}, WaylandSurface}, shm::{slot::SlotPool, Shm}};
-use wayland_client::{globals::registry_queue_init, Connection};
+use hyogen_ui::{
+    cli::{self, Cli, RunArgs}, config::Config, control, events::EventFilter, face::FaceState, headless,
+    hvf::loader::{HVFLoader, BUILTIN_CLASS}, supervisor, terminal,
+};
 
-use std::{env, time::Duration};
+#[cfg(feature = "export")]
+use hyogen_ui::export;
+
+use calloop::{signals::{Signal, Signals}, EventLoop};
+use std::{env, io, path::PathBuf, process};
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let cli = match cli::parse(&args) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            process::exit(2);
+        }
+    };
+
+    // With --stdin, stdout carries responses and events, so logs go to stderr
+    let stdin = matches!(&cli, Cli::Run(run) if run.stdin);
+    let writer = match stdin {
+        true => tracing_subscriber::fmt::writer::BoxMakeWriter::new(io::stderr),
+        false => tracing_subscriber::fmt::writer::BoxMakeWriter::new(io::stdout),
+    };
     if let Ok(env_filter) = tracing_subscriber::EnvFilter::try_from_default_env() {
         tracing_subscriber::fmt()
             .compact()
+            .with_writer(writer)
             .with_env_filter(env_filter)
             .init();
     } else {
-        tracing_subscriber::fmt().compact().init();
+        tracing_subscriber::fmt().compact().with_writer(writer).init();
     }
-    
-    // Retrive hvf file path from CLI.
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        tracing::error!("hvf file was not provided!");
-        tracing::info!("Usage: {} <path_to_hvf_file>", args[0]);
-        return;
-    }
-    
-    tracing::info!("Welcome to Hyogen UI");
-
-    // All wayland clients start by connecting to the compositor (Aurora).
-    let conn = Connection::connect_to_env().unwrap();
-
-    // Enumerate the list of glovals to get the protocols the server implements.
-    let (globals, mut event_queue) = registry_queue_init(&conn).unwrap();
-    let qh = event_queue.handle();
-
-    // The compositor allows configuring surfaces to be presented.
-    let compositor = CompositorState::bind(&globals, &qh).expect("wl_compositor is not availabel");
-
-    // HyogenUI uses the wlr layer shell, so make sure the compositor supports it. (Aurora does)
-    let layer_shell = LayerShell::bind(&globals, &qh).expect("layer shell is not available");
-
-    // Using wl_shm to allow software rendering to a buffer, shared with the compositor process.
-    // TODO: Use GPU for rendering.
-    let shm = Shm::bind(&globals, &qh).expect("wl_shm is not available");
-
-    // A layer surface is created from a surface.
-    let surface = compositor.create_surface(&qh);
-    // And then we create a layer shell
-    let layer = layer_shell.create_layer_surface(&qh, surface, Layer::Background, Some("Hyogen_UI"), None);
-
-    // Configure the layer surface, providing things like the anchor on screen, desired size, etc.
-    layer.set_anchor(Anchor::TOP);
-    layer.set_size(800, 480);
-
-    // In order for layer surface to be mapped, we need to perform an initial commit with no attached buffer
-    // The compositor will respond with an initial configure that we can then use to present to the layer
-    // surface with the correct options.
-    layer.commit();
-
-    // We don't know how large the window will be yet, so lets assume the minimum size we suggested for the initial memory allocation.
-    let pool = SlotPool::new(800 * 480 * 4, &shm).expect("failed to create pool");
-    
-    // Load hvf files;
-    let hvf_loader = hvf::loader::HVFLoader::new(&args[1]).unwrap();
-
-    let default_ring = hvf_loader.get("expression", "neutral").unwrap();
-    let blink_ring = hvf_loader.get("expression", "blink").unwrap();
-
-    // Initialize hyogen layer
-    let mut hyogen_layer = HyogenLayer::new(layer, &globals, &qh, shm, pool, default_ring);
 
-    hyogen_layer.animator.animate(blink_ring, Duration::from_secs_f64(1.4));
-    // let blink_animation = Blink::new(blink_ring, &hyogen_layer.animator);
-    // blink_animation.start();
+    match cli {
+        Cli::Help(usage) => println!("{}", usage),
+        Cli::Run(run) => run_face(run),
+        Cli::Config(path) => check_config(path),
+
+        // Offline rendering doesn't need a compositor.
+        Cli::Render(args) => {
+            let result = headless::RenderOptions::from_args(&args)
+                .and_then(|options| headless::render_to_files(&options).map(|count| (count, options)));
+
+            match result {
+                Ok((count, options)) => tracing::info!("Rendered {} frames to {}", count, options.out.display()),
+                Err(err) => {
+                    tracing::error!("Render failed: {:?}", err);
+                    process::exit(1);
+                }
+            }
+        }
+        #[cfg(feature = "export")]
+        Cli::Export(args) => {
+            let result = export::ExportOptions::from_args(&args)
+                .and_then(|options| export::export(&options).map(|count| (count, options)));
+
+            match result {
+                Ok((count, options)) => tracing::info!("Exported {} frames to {}", count, options.render.out.display()),
+                Err(err) => {
+                    tracing::error!("Export failed: {:?}", err);
+                    process::exit(1);
+                }
+            }
+        }
+        #[cfg(not(feature = "export"))]
+        Cli::Export(_) => {
+            tracing::error!("This build of hyogen-ui can't export animations, it was built without the export feature");
+            process::exit(1);
+        }
+        // Preview in the terminal, e.g. over SSH or in CI logs.
+        Cli::Preview(args) => {
+            let result = terminal::PreviewOptions::from_args(&args)
+                .and_then(|options| match preview_signals() {
+                    Ok(mut signals) => terminal::preview(&options, |timeout| {
+                        // Ctrl-C ends the preview, through its guard restoring the terminal
+                        let mut interrupted = false;
+                        let _ = signals.dispatch(timeout, &mut interrupted);
+                        !interrupted
+                    }),
+                    Err(err) => {
+                        tracing::warn!("Ctrl-C may leave the cursor hidden: {}", err);
+                        terminal::preview(&options, terminal::sleep)
+                    }
+                });
+
+            if let Err(err) = result {
+                tracing::error!("Preview failed: {:?}", err);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+// An event loop that only waits for SIGINT and SIGTERM, setting the flag it's dispatched with.
+fn preview_signals() -> calloop::Result<EventLoop<'static, bool>> {
+    let event_loop = EventLoop::try_new()?;
+    let signals = Signals::new(&[Signal::SIGINT, Signal::SIGTERM])?;
+    event_loop.handle()
+        .insert_source(signals, |_, _, interrupted| *interrupted = true)
+        .map_err(|err| err.error)?;
+    Ok(event_loop)
+}
+
+// Settings from the config file and the command line, exits if they're invalid.
+fn load_config(run: &RunArgs) -> Config {
+    match run.resolve() {
+        Ok(config) => config,
+        Err(err) => {
+            match run.config_path() {
+                Some(path) => tracing::error!("Invalid settings ({}): {}", path.display(), err),
+                None => tracing::error!("Invalid settings: {}", err),
+            }
+            process::exit(1);
+        }
+    }
+}
+
+fn check_config(path: Option<PathBuf>) {
+    let mut run = RunArgs::default();
+    run.config = path;
+    let config = load_config(&run);
+    match run.config_path() {
+        Some(path) => println!("Using {}", path.display()),
+        None => println!("No config file found, using the built-in defaults"),
+    }
+    println!("{:#?}", config);
+}
 
-    // We don't draw immediately, the configure will notify us when to first draw.
-    loop {
-        event_queue.blocking_dispatch(&mut hyogen_layer).unwrap();
+fn run_face(run: RunArgs) {
+    tracing::info!("Welcome to Hyogen UI");
+    let config = load_config(&run);
+    if let Some(path) = run.config_path() {
+        tracing::info!("Using the settings of {}", path.display());
+    }
 
-        if hyogen_layer.exit() {
-            tracing::info!("Exiting Hyogen UI");
-            break;
+    // Load hvf files, the first one that works is used.
+    if config.hvf.is_empty() {
+        tracing::error!("No HVF file given, pass one or set face.hvf in the config\n\n{}", cli::RUN_USAGE);
+        process::exit(2);
+    }
+    let loaded = config.hvf.iter().find_map(|path| match HVFLoader::new(&path.to_string_lossy()) {
+        Ok(loader) if loader.get(&config.class, &config.expression).is_some() => Some((path, loader)),
+        Ok(_) => {
+            tracing::error!("{} has no {} expression {}", path.display(), config.class, config.expression);
+            None
+        }
+        Err(err) => {
+            tracing::error!("Failed to load {}: {:?}", path.display(), err);
+            None
+        }
+    });
+
+    // The face, independent of the connection to the compositor
+    let mut face = match loaded {
+        Some((hvf_path, hvf_loader)) => {
+            let mut face = FaceState::new(hvf_loader.get(&config.class, &config.expression).unwrap(), config.placement.clone());
+            face.expression = config.expression.clone();
+            face.hvf_path = Some(hvf_path.to_string_lossy().into_owned());
+            // Kept around to switch expressions by name over the control socket
+            face.hvf = Some(hvf_loader);
+            face
+        }
+        // Rather than a blank surface, show that something's wrong until a file loads on reload
+        None => {
+            tracing::error!("Showing the built-in face until an HVF file loads");
+            let hvf_loader = HVFLoader::builtin();
+            let mut face = FaceState::new(hvf_loader.get(BUILTIN_CLASS, "error").unwrap(), config.placement.clone());
+            face.expression = "error".to_string();
+            face.hvf = Some(hvf_loader);
+            face.fallback = true;
+            face
         }
+    };
+    face.events.subscribe(EventFilter::All, |event| {
+        tracing::debug!("{:?}", event);
+        true
+    });
+
+    // Theme, blink, frame cap... the same way they're changed on reload
+    face.configure(config.clone());
+    face.settings = Some(run.clone());
+
+    // --stdin takes commands as JSON lines on stdin, with answers and events on stdout.
+    let stdin = run.stdin.then(|| {
+        face.events.subscribe(EventFilter::All, control::json_lines(io::stdout()));
+        control::stdin::StdinScript::start()
+    });
+    let options = supervisor::SessionOptions {
+        supervise: config.supervise,
+        window: config.window,
+        control_socket: config.ipc.socket_path(),
+        dbus: config.ipc.dbus,
+        stdin,
+    };
+    if let Err(err) = supervisor::run(face, options) {
+        tracing::error!("{}", err);
+        process::exit(1);
     }
-}
\ No newline at end of file
+    tracing::info!("Exiting Hyogen UI");
+}
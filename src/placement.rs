@@ -0,0 +1,185 @@
+use std::str::FromStr;
+
+use smithay_client_toolkit::{output::OutputInfo, shell::wlr_layer::{Anchor, KeyboardInteractivity, Layer, LayerSurface}};
+
+// Distance kept from the anchored edges, in surface-local pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Margin {
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+    pub left: i32,
+}
+
+impl FromStr for Margin {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        // CSS style: `all`, `vertical,horizontal` or `top,right,bottom,left`
+        let values = value
+            .split(',')
+            .map(|part| part.trim().parse::<i32>().ok())
+            .collect::<Option<Vec<i32>>>()
+            .ok_or_else(|| format!("Invalid margin {}", value))?;
+
+        match values[..] {
+            [all] => Ok(Margin { top: all, right: all, bottom: all, left: all }),
+            [vertical, horizontal] => Ok(Margin { top: vertical, right: horizontal, bottom: vertical, left: horizontal }),
+            [top, right, bottom, left] => Ok(Margin { top, right, bottom, left }),
+            _ => Err(format!("Invalid margin {}, expected 1, 2 or 4 values", value)),
+        }
+    }
+}
+
+// Which outputs get a face.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum OutputSelector {
+    #[default]
+    Auto,                   // A single surface, on whichever output the compositor picks
+    All,
+    Matching(Vec<String>),  // Output names (e.g. DP-1) or parts of their description
+}
+
+impl OutputSelector {
+    pub fn matches(&self, info: &OutputInfo) -> bool {
+        match self {
+            OutputSelector::Auto => false,
+            OutputSelector::All => true,
+            OutputSelector::Matching(patterns) => patterns.iter().any(|pattern| {
+                info.name.as_deref() == Some(pattern.as_str())
+                    || info.description.as_deref().is_some_and(|description| description.contains(pattern.as_str()))
+            }),
+        }
+    }
+}
+
+impl FromStr for OutputSelector {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        // `auto`, `all` or a comma separated list of names / descriptions
+        match value {
+            "auto" => return Ok(OutputSelector::Auto),
+            "all" => return Ok(OutputSelector::All),
+            _ => {}
+        }
+
+        let patterns: Vec<String> = value.split(',').map(str::trim).filter(|pattern| !pattern.is_empty()).map(String::from).collect();
+        if patterns.is_empty() {
+            return Err(format!("Invalid output {}, expected auto, all or a list of output names", value));
+        }
+        Ok(OutputSelector::Matching(patterns))
+    }
+}
+
+// Where and how the face's layer surface is placed by the compositor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    pub layer: Layer,
+    pub anchor: Anchor,
+    pub margin: Margin,
+    pub size: (u32, u32),                      // 0 stretches between the anchored edges
+    pub exclusive_zone: i32,
+    pub keyboard: KeyboardInteractivity,
+    pub namespace: String,
+    pub outputs: OutputSelector,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Self {
+            layer: Layer::Background,
+            anchor: Anchor::TOP,
+            margin: Margin::default(),
+            size: (800, 480),
+            exclusive_zone: 0,
+            keyboard: KeyboardInteractivity::None,
+            namespace: "Hyogen_UI".to_string(),
+            outputs: OutputSelector::Auto,
+        }
+    }
+}
+
+pub fn parse_layer(value: &str) -> Result<Layer, String> {
+    match value {
+        "background" => Ok(Layer::Background),
+        "bottom" => Ok(Layer::Bottom),
+        "top" => Ok(Layer::Top),
+        "overlay" => Ok(Layer::Overlay),
+        _ => Err(format!("Unknown layer {}, expected background, bottom, top or overlay", value)),
+    }
+}
+
+pub fn parse_anchor(value: &str) -> Result<Anchor, String> {
+    // Comma separated edges, or `all` / `none`
+    match value {
+        "all" => return Ok(Anchor::all()),
+        "none" => return Ok(Anchor::empty()),
+        _ => {}
+    }
+
+    value.split(',').map(str::trim).try_fold(Anchor::empty(), |anchor, edge| {
+        let edge = match edge {
+            "top" => Anchor::TOP,
+            "bottom" => Anchor::BOTTOM,
+            "left" => Anchor::LEFT,
+            "right" => Anchor::RIGHT,
+            _ => return Err(format!("Unknown anchor edge {}", edge)),
+        };
+        Ok(anchor | edge)
+    })
+}
+
+pub fn parse_keyboard(value: &str) -> Result<KeyboardInteractivity, String> {
+    match value {
+        "none" => Ok(KeyboardInteractivity::None),
+        "exclusive" => Ok(KeyboardInteractivity::Exclusive),
+        "on-demand" => Ok(KeyboardInteractivity::OnDemand),
+        _ => Err(format!("Unknown keyboard interactivity {}, expected none, exclusive or on-demand", value)),
+    }
+}
+
+pub fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("Invalid size {}, expected WIDTHxHEIGHT", value);
+    let (width, height) = value.split_once('x').ok_or_else(invalid)?;
+    Ok((width.parse().map_err(|_| invalid())?, height.parse().map_err(|_| invalid())?))
+}
+
+impl Placement {
+    // Consume `flag` if it is a placement flag, returns false for unknown flags.
+    pub fn apply_flag(&mut self, flag: &str, value: &str) -> Result<bool, String> {
+        match flag {
+            "--layer" => self.layer = parse_layer(value)?,
+            "--anchor" => self.anchor = parse_anchor(value)?,
+            "--margin" => self.margin = value.parse()?,
+            "--size" => self.size = parse_size(value)?,
+            "--exclusive-zone" => self.exclusive_zone = value.parse().map_err(|_| format!("Invalid exclusive zone {}", value))?,
+            "--keyboard" => self.keyboard = parse_keyboard(value)?,
+            "--namespace" => self.namespace = value.to_string(),
+            "--output" => self.outputs = value.parse()?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // Errors name the setting at fault as it's called in the config file, like "size".
+    pub fn validate(&self) -> Result<(), (&'static str, String)> {
+        // A zero size is only valid when the surface is anchored to both opposite edges
+        if self.size.0 == 0 && !self.anchor.contains(Anchor::LEFT | Anchor::RIGHT) {
+            return Err(("size", "A width of 0 requires anchoring to both left and right".to_string()));
+        }
+        if self.size.1 == 0 && !self.anchor.contains(Anchor::TOP | Anchor::BOTTOM) {
+            return Err(("size", "A height of 0 requires anchoring to both top and bottom".to_string()));
+        }
+        Ok(())
+    }
+
+    // Send everything except the layer and namespace, which are given when the surface is created.
+    pub fn apply(&self, layer: &LayerSurface) {
+        layer.set_anchor(self.anchor);
+        layer.set_size(self.size.0, self.size.1);
+        layer.set_margin(self.margin.top, self.margin.right, self.margin.bottom, self.margin.left);
+        layer.set_exclusive_zone(self.exclusive_zone);
+        layer.set_keyboard_interactivity(self.keyboard);
+    }
+}
@@ -0,0 +1,150 @@
+use super::Frame;
+
+// Axis aligned pixel rectangle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub const fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0 || self.height <= 0
+    }
+
+    pub fn right(&self) -> i32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height
+    }
+
+    pub fn contains(&self, x: i64, y: i64) -> bool {
+        x >= self.x as i64 && y >= self.y as i64 && x < self.right() as i64 && y < self.bottom() as i64
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect::new(x, y, self.right().max(other.right()) - x, self.bottom().max(other.bottom()) - y)
+    }
+
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+
+        if right <= x || bottom <= y {
+            return Rect::default();
+        }
+        Rect::new(x, y, right - x, bottom - y)
+    }
+}
+
+// Pixel area covered by everything the frame draws on a surface of the given size.
+pub fn frame_bounds(frame: &Frame, width: u32, height: u32) -> Rect {
+    let transform = frame.transform;
+    let mut bounds = Rect::default();
+
+    for (i, path) in frame.paths.paths().enumerate() {
+        let style = frame.style(i);
+
+        let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+        let (mut max_x, mut max_y) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for point in path {
+            let (x, y) = transform.to_surface(point.x, point.y);
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        if !min_x.is_finite() || !min_y.is_finite() || !max_x.is_finite() || !max_y.is_finite() {
+            continue;
+        }
+
+        // Strokes spill over the path by half their width, plus a pixel for rounding
+        let pad = (style.stroke_width * transform.length_scale() / 2.0).max(0.5) + 1.0;
+        let x = (min_x - pad).floor() as i32;
+        let y = (min_y - pad).floor() as i32;
+        let path_bounds = Rect::new(x, y, (max_x + pad).ceil() as i32 - x, (max_y + pad).ceil() as i32 - y);
+
+        bounds = bounds.union(&path_bounds);
+    }
+
+    bounds.intersect(&Rect::new(0, 0, width as i32, height as i32))
+}
+
+// Remembers what was last presented to work out which pixels the next frame touches.
+#[derive(Debug, Default)]
+pub struct DamageTracker {
+    last_frame: Option<Frame>,
+    last_bounds: Rect,
+    last_size: (u32, u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Damage {
+    None,           // The frame is identical to what's on screen
+    Partial(Rect),  // Only this region needs to be cleared and redrawn
+    Full,
+}
+
+impl Damage {
+    pub fn union(self, other: Damage) -> Damage {
+        match (self, other) {
+            (Damage::Full, _) | (_, Damage::Full) => Damage::Full,
+            (Damage::None, damage) | (damage, Damage::None) => damage,
+            (Damage::Partial(a), Damage::Partial(b)) => Damage::Partial(a.union(&b)),
+        }
+    }
+}
+
+impl DamageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Work out the damage of `frame` and remember it as the presented frame.
+    pub fn update(&mut self, frame: &Frame, width: u32, height: u32) -> Damage {
+        let bounds = frame_bounds(frame, width, height);
+
+        let damage = match &self.last_frame {
+            _ if self.last_size != (width, height) => Damage::Full,
+            None => Damage::Full,
+            Some(last) if last.background != frame.background || last.transform != frame.transform => Damage::Full,
+            Some(last) if last.paths == frame.paths && last.styles == frame.styles => Damage::None,
+            Some(_) => {
+                let region = self.last_bounds.union(&bounds);
+                if region.is_empty() { Damage::None } else { Damage::Partial(region) }
+            }
+        };
+
+        match &mut self.last_frame {
+            Some(last) => last.clone_from(frame),
+            None => self.last_frame = Some(frame.clone()),
+        }
+        self.last_bounds = bounds;
+        self.last_size = (width, height);
+        damage
+    }
+
+    // Forget the presented frame, the next update will report full damage.
+    pub fn reset(&mut self) {
+        self.last_frame = None;
+    }
+}
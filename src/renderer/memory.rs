@@ -0,0 +1,62 @@
+use super::{Color, Frame, PixelBuffer, Renderer};
+
+// Renders into a plain heap allocated buffer, no compositor required.
+pub struct MemoryBackend {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl MemoryBackend {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.data.resize(width as usize * height as usize * 4, 0);
+    }
+
+    pub fn buffer(&mut self) -> PixelBuffer<'_> {
+        PixelBuffer::new(&mut self.data, self.width, self.height, self.width * 4)
+    }
+
+    pub fn draw(&mut self, renderer: &mut dyn Renderer, frame: &Frame) {
+        let mut target = self.buffer();
+        renderer.render(frame, &mut target);
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let index = (y as usize * self.width as usize + x as usize) * 4;
+        Color::from_argb8888(&self.data[index..index + 4])
+    }
+
+    // Raw Argb8888 bytes, rows are tightly packed.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    // Pixels converted to straight RGBA, as expected by most image encoders.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.data
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                let color = Color::from_argb8888(pixel);
+                [color.r, color.g, color.b, color.a]
+            })
+            .collect()
+    }
+}
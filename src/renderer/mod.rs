@@ -0,0 +1,239 @@
+pub mod damage;
+pub mod memory;
+pub mod raster;
+#[cfg(feature = "wayland")]
+pub mod shm;
+
+// Size of the coordinate space HVF paths are authored in.
+pub const DESIGN_WIDTH: f64 = 800.0;
+pub const DESIGN_HEIGHT: f64 = 480.0;
+
+use std::str::FromStr;
+
+use damage::Rect;
+
+use crate::{geometry::Shape, layout::Transform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+    pub const BLACK: Color = Color::rgba(0, 0, 0, 0xFF);
+    pub const WHITE: Color = Color::rgba(0xFF, 0xFF, 0xFF, 0xFF);
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub fn to_argb8888(self) -> [u8; 4] {
+        // wl_shm Argb8888 is little endian, so bytes are laid out as B, G, R, A
+        [self.b, self.g, self.r, self.a]
+    }
+
+    pub fn from_argb8888(bytes: &[u8]) -> Self {
+        Self::rgba(bytes[2], bytes[1], bytes[0], bytes[3])
+    }
+
+    // Color channels scaled by alpha, the way compositors expect wl_shm buffers.
+    pub fn premultiplied(self) -> Self {
+        let scale = |channel: u8| ((channel as u16 * self.a as u16 + 127) / 255) as u8;
+        Self::rgba(scale(self.r), scale(self.g), scale(self.b), self.a)
+    }
+
+    pub fn unpremultiplied(self) -> Self {
+        if self.a == 0 {
+            return Color::TRANSPARENT;
+        }
+        let scale = |channel: u8| ((channel as u16 * 255 + self.a as u16 / 2) / self.a as u16).min(255) as u8;
+        Self::rgba(scale(self.r), scale(self.g), scale(self.b), self.a)
+    }
+
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        // Accepts RRGGBB or RRGGBBAA, with or without a leading '#'
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.is_ascii() {
+            return None;
+        }
+
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        match hex.len() {
+            6 => Some(Self::rgba(channel(0)?, channel(2)?, channel(4)?, 0xFF)),
+            8 => Some(Self::rgba(channel(0)?, channel(2)?, channel(4)?, channel(6)?)),
+            _ => None,
+        }
+    }
+}
+
+// How the outline of a path is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outline {
+    Points,     // One pixel per point of the path, the way faces have always been drawn
+    Stroke,     // The points joined into a closed line `stroke_width` wide
+}
+
+impl FromStr for Outline {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "points" => Ok(Outline::Points),
+            "stroke" => Ok(Outline::Stroke),
+            _ => Err(format!("Unknown outline {}, expected points or stroke", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    pub outline: Outline,
+    pub stroke: Color,
+    pub stroke_width: f64,          // In design units, scaled with the surface
+    pub fill: Option<Color>,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            outline: Outline::Points,
+            stroke: Color::WHITE,
+            stroke_width: 1.0,
+            fill: None,
+        }
+    }
+}
+
+// Everything a renderer needs to produce one frame.
+#[derive(Debug, PartialEq)]
+pub struct Frame {
+    pub paths: Shape,               // Animated paths in design space
+    pub styles: Vec<Style>,         // Per path style, the last one is reused for any remaining paths
+    pub background: Color,
+    pub transform: Transform,       // Design space to target pixels
+}
+
+impl Clone for Frame {
+    fn clone(&self) -> Self {
+        Self { paths: self.paths.clone(), styles: self.styles.clone(), background: self.background, transform: self.transform }
+    }
+
+    // Reuses the buffers, see `DamageTracker::update`
+    fn clone_from(&mut self, source: &Self) {
+        self.paths.clone_from(&source.paths);
+        self.styles.clone_from(&source.styles);
+        self.background = source.background;
+        self.transform = source.transform;
+    }
+}
+
+impl Frame {
+    pub fn new(paths: Shape, styles: Vec<Style>, background: Color) -> Self {
+        Self { paths, styles, background, transform: Transform::default() }
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn style(&self, index: usize) -> Style {
+        self.styles
+            .get(index)
+            .or(self.styles.last())
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+// A borrowed Argb8888 pixel buffer a renderer draws into.
+pub struct PixelBuffer<'a> {
+    data: &'a mut [u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    clip: Rect,                     // Pixels outside the clip are never touched
+    premultiplied: bool,            // Colors are stored premultiplied by alpha
+}
+
+impl<'a> PixelBuffer<'a> {
+    pub fn new(data: &'a mut [u8], width: u32, height: u32, stride: u32) -> Self {
+        assert!(stride >= width * 4, "Stride must fit a full row of pixels");
+        assert!(data.len() >= (stride * height) as usize, "Buffer is too small for the given size");
+
+        Self { data, width, height, stride, clip: Rect::new(0, 0, width as i32, height as i32), premultiplied: false }
+    }
+
+    // Store colors premultiplied by alpha, as wl_shm Argb8888 is. Drawing and reading still use plain colors.
+    pub fn with_premultiplied_alpha(mut self) -> Self {
+        self.premultiplied = true;
+        self
+    }
+
+    fn encode(&self, color: Color) -> [u8; 4] {
+        match self.premultiplied {
+            true => color.premultiplied().to_argb8888(),
+            false => color.to_argb8888(),
+        }
+    }
+
+    // Restrict drawing to `clip`, used for partial redraws.
+    pub fn set_clip(&mut self, clip: Rect) {
+        self.clip = clip.intersect(&Rect::new(0, 0, self.width as i32, self.height as i32));
+    }
+
+    pub fn clip(&self) -> Rect {
+        self.clip
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        // Only the clipped region is cleared
+        let pixel = self.encode(color);
+        let (start, end) = (self.clip.x as usize * 4, self.clip.right() as usize * 4);
+        let rows = self.data.chunks_mut(self.stride as usize)
+            .skip(self.clip.y as usize)
+            .take(self.clip.height.max(0) as usize);
+
+        for row in rows {
+            for chunk in row[start..end].chunks_exact_mut(4) {
+                chunk.copy_from_slice(&pixel);
+            }
+        }
+    }
+
+    pub fn put_pixel(&mut self, x: i64, y: i64, color: Color) {
+        if !self.clip.contains(x, y) {
+            return;
+        }
+
+        let index = y as usize * self.stride as usize + x as usize * 4;
+        let pixel = self.encode(color);
+        self.data[index..index + 4].copy_from_slice(&pixel);
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let index = y as usize * self.stride as usize + x as usize * 4;
+        let color = Color::from_argb8888(&self.data[index..index + 4]);
+        match self.premultiplied {
+            true => color.unpremultiplied(),
+            false => color,
+        }
+    }
+}
+
+pub trait Renderer {
+    // Draw the frame into the target, replacing its previous content inside the target's clip.
+    fn render(&mut self, frame: &Frame, target: &mut PixelBuffer);
+}
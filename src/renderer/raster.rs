@@ -0,0 +1,115 @@
+use crate::{geometry::Point, layout::Transform};
+
+use super::{Color, Frame, Outline, PixelBuffer, Renderer};
+
+// CPU rasterizer drawing closed paths as filled polygons, outlined by their points or a stroke.
+#[derive(Debug, Default)]
+pub struct SoftwareRenderer {
+    points: Vec<(f64, f64)>,    // Scratch space kept between frames, so drawing doesn't allocate
+    crossings: Vec<f64>,
+}
+
+impl SoftwareRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn to_surface(path: &[Point], transform: &Transform, points: &mut Vec<(f64, f64)>) {
+        points.clear();
+        points.extend(path.iter().map(|point| transform.to_surface(point.x, point.y)));
+    }
+
+    fn fill(target: &mut PixelBuffer, points: &[(f64, f64)], crossings: &mut Vec<f64>, color: Color) {
+        // Even-odd scanline fill, sampling each row at the pixel center
+        if points.len() < 3 {
+            return;
+        }
+
+        let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min).floor().max(0.0) as i64;
+        let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max).ceil().min(target.height() as f64) as i64;
+
+        for y in min_y..max_y {
+            let scan = y as f64 + 0.5;
+            crossings.clear();
+
+            let mut prev = points[points.len() - 1];
+            for &point in points {
+                let (a, b) = (prev, point);
+                if (a.1 <= scan && b.1 > scan) || (b.1 <= scan && a.1 > scan) {
+                    crossings.push(a.0 + (scan - a.1) / (b.1 - a.1) * (b.0 - a.0));
+                }
+                prev = point;
+            }
+
+            crossings.sort_by(|a, b| a.total_cmp(b));
+            for span in crossings.chunks_exact(2) {
+                let start = (span[0] - 0.5).ceil() as i64;
+                let end = (span[1] - 0.5).floor() as i64;
+                for x in start..=end {
+                    target.put_pixel(x, y, color);
+                }
+            }
+        }
+    }
+
+    fn stroke(target: &mut PixelBuffer, points: &[(f64, f64)], width: f64, color: Color) {
+        // Stamp a disc every half pixel along each segment of the closed path
+        let radius = (width / 2.0).max(0.5);
+        let mut prev = match points.last() {
+            Some(point) => *point,
+            None => return,
+        };
+
+        for &point in points {
+            let (dx, dy) = (point.0 - prev.0, point.1 - prev.1);
+            let steps = ((dx * dx + dy * dy).sqrt() * 2.0).ceil().max(1.0) as usize;
+            for step in 0..=steps {
+                let t = step as f64 / steps as f64;
+                Self::stamp(target, prev.0 + dx * t, prev.1 + dy * t, radius, color);
+            }
+            prev = point;
+        }
+    }
+
+    fn plot(target: &mut PixelBuffer, points: &[(f64, f64)], color: Color) {
+        for &(x, y) in points {
+            target.put_pixel(x.floor() as i64, y.floor() as i64, color);
+        }
+    }
+
+    fn stamp(target: &mut PixelBuffer, cx: f64, cy: f64, radius: f64, color: Color) {
+        if radius <= 0.5 {
+            target.put_pixel(cx.floor() as i64, cy.floor() as i64, color);
+            return;
+        }
+
+        let r2 = radius * radius;
+        for y in (cy - radius).floor() as i64..=(cy + radius).ceil() as i64 {
+            for x in (cx - radius).floor() as i64..=(cx + radius).ceil() as i64 {
+                let (dx, dy) = (x as f64 + 0.5 - cx, y as f64 + 0.5 - cy);
+                if dx * dx + dy * dy <= r2 {
+                    target.put_pixel(x, y, color);
+                }
+            }
+        }
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    fn render(&mut self, frame: &Frame, target: &mut PixelBuffer) {
+        target.clear(frame.background);
+
+        for (i, path) in frame.paths.paths().enumerate() {
+            let style = frame.style(i);
+            Self::to_surface(path, &frame.transform, &mut self.points);
+
+            if let Some(fill) = style.fill {
+                Self::fill(target, &self.points, &mut self.crossings, fill);
+            }
+            match style.outline {
+                Outline::Points => Self::plot(target, &self.points, style.stroke),
+                Outline::Stroke => Self::stroke(target, &self.points, style.stroke_width * frame.transform.length_scale(), style.stroke),
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use smithay_client_toolkit::shm::slot::{Buffer, SlotPool};
+use wayland_client::protocol::{wl_shm, wl_surface::WlSurface};
+
+use super::{damage::{Damage, DamageTracker}, Frame, PixelBuffer, Renderer};
+
+// Number of buffers kept around, enough for the compositor to hold one while we draw the next.
+const RING_SIZE: usize = 3;
+
+struct RingBuffer {
+    buffer: Buffer,
+    stale: Damage,     // Region that changed since this buffer was last drawn
+}
+
+// Renders into wl_shm buffers shared with the compositor.
+pub struct ShmBackend {
+    pool: SlotPool,
+    buffers: Vec<RingBuffer>,  // Ordered from least to most recently used
+    damage: DamageTracker,
+    width: u32,
+    height: u32,
+}
+
+impl ShmBackend {
+    pub fn new(pool: SlotPool) -> Self {
+        Self {
+            pool,
+            buffers: Vec::with_capacity(RING_SIZE),
+            damage: DamageTracker::new(),
+            width: 0,
+            height: 0,
+        }
+    }
+
+    pub fn pool(&mut self) -> &mut SlotPool {
+        &mut self.pool
+    }
+
+    // Prepare for a new surface size, growing the pool so the whole ring fits.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+
+        let needed = width as usize * height as usize * 4 * RING_SIZE;
+        if self.pool.len() < needed {
+            if let Err(err) = self.pool.resize(needed) {
+                tracing::error!("Failed to grow the shm pool to {} bytes: {}", needed, err);
+            }
+        }
+
+        // Old buffers have the wrong size, their slots go back to the pool once released
+        self.buffers.clear();
+        self.damage.reset();
+    }
+
+    // Forget all buffers, e.g. when the surface they were attached to is gone.
+    pub fn reset(&mut self) {
+        self.buffers.clear();
+        self.damage.reset();
+    }
+
+    // Render a frame and attach it to the surface, the caller is responsible for committing.
+    // Returns false when the frame is identical to what's on screen and nothing was attached.
+    pub fn draw(&mut self, renderer: &mut dyn Renderer, frame: &Frame, surface: &WlSurface, width: u32, height: u32) -> bool {
+        self.resize(width, height);
+        let stride = width * 4;
+
+        let damage = self.damage.update(frame, width, height);
+        if damage == Damage::None {
+            return false;
+        }
+
+        // Reuse a buffer the compositor released, otherwise grow the ring (or replace its oldest entry)
+        let pool = &mut self.pool;
+        let index = match self.buffers.iter().position(|ring| ring.buffer.canvas(pool).is_some()) {
+            Some(index) => index,
+            None => {
+                if self.buffers.len() == RING_SIZE {
+                    self.buffers.remove(0);
+                }
+
+                let (buffer, _) = self.pool
+                    .create_buffer(width as i32, height as i32, stride as i32, wl_shm::Format::Argb8888)
+                    .expect("create buffer");
+                self.buffers.push(RingBuffer { buffer, stale: Damage::Full });
+                self.buffers.len() - 1
+            }
+        };
+
+        // Every other buffer falls behind by this frame's damage
+        let mut ring = self.buffers.remove(index);
+        for other in self.buffers.iter_mut() {
+            other.stale = other.stale.union(damage);
+        }
+        let region = ring.stale.union(damage);
+        ring.stale = Damage::None;
+
+        let canvas = ring.buffer.canvas(&mut self.pool).expect("buffer canvas");
+        // Compositors read Argb8888 as premultiplied alpha
+        let mut target = PixelBuffer::new(canvas, width, height, stride).with_premultiplied_alpha();
+
+        match region {
+            Damage::Partial(region) => {
+                target.set_clip(region);
+                renderer.render(frame, &mut target);
+                surface.damage_buffer(region.x, region.y, region.width, region.height);
+            }
+            _ => {
+                renderer.render(frame, &mut target);
+                surface.damage_buffer(0, 0, width as i32, height as i32);
+            }
+        }
+
+        ring.buffer.attach_to(surface).expect("buffer attach");
+        self.buffers.push(ring);
+        true
+    }
+}
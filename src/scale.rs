@@ -0,0 +1,141 @@
+use smithay_client_toolkit::output::OutputState;
+use wayland_client::{globals::GlobalList, protocol::{wl_output::WlOutput, wl_surface::WlSurface}, Connection, Dispatch, QueueHandle};
+use wayland_protocols::wp::{
+    fractional_scale::v1::client::{wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1, wp_fractional_scale_v1::{self, WpFractionalScaleV1}},
+    viewporter::client::{wp_viewport::WpViewport, wp_viewporter::WpViewporter}
+};
+
+use crate::layer::HyogenLayer;
+
+// Globals needed for fractional scaling, shared by every surface.
+pub struct ScaleManagers(Option<(WpFractionalScaleManagerV1, WpViewporter)>);  // Only used when both protocols are available
+
+impl ScaleManagers {
+    pub fn bind(globals: &GlobalList, qh: &QueueHandle<HyogenLayer>) -> Self {
+        let manager = globals.bind::<WpFractionalScaleManagerV1, _, _>(qh, 1..=1, ()).ok();
+        let viewporter = globals.bind::<WpViewporter, _, _>(qh, 1..=1, ()).ok();
+
+        let managers = manager.zip(viewporter);
+        if managers.is_some() {
+            tracing::info!("Using fractional scaling");
+        }
+        Self(managers)
+    }
+}
+
+// Tracks the scale a surface should be rendered at.
+pub struct ScaleState {
+    fractional: Option<(WpFractionalScaleV1, WpViewport)>,         // Per surface objects created from the managers
+    integer_scale: i32,                                    // Largest scale of the outputs the surface is on
+    fractional_scale: Option<u32>,                         // Preferred scale in 120ths, from wp_fractional_scale_v1
+    entered: Vec<WlOutput>,                                // Outputs the surface is currently shown on
+}
+
+impl ScaleState {
+    pub fn new(managers: &ScaleManagers, surface: &WlSurface, qh: &QueueHandle<HyogenLayer>) -> Self {
+        // The surface is passed along so scale events can be routed to the right face
+        let fractional = managers.0.as_ref().map(|(manager, viewporter)| (
+            manager.get_fractional_scale(surface, qh, surface.clone()),
+            viewporter.get_viewport(surface, qh, ()),
+        ));
+
+        Self {
+            fractional,
+            integer_scale: 1,
+            fractional_scale: None,
+            entered: Vec::new(),
+        }
+    }
+
+    // Effective scale factor, fractional when the compositor supports it.
+    pub fn factor(&self) -> f64 {
+        match (self.fractional.is_some(), self.fractional_scale) {
+            (true, Some(scale)) => scale as f64 / 120.0,
+            _ => self.integer_scale as f64,
+        }
+    }
+
+    // Size of the buffer backing a surface of the given logical size.
+    pub fn buffer_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let factor = self.factor();
+        (
+            ((width as f64 * factor).round() as u32).max(1),
+            ((height as f64 * factor).round() as u32).max(1),
+        )
+    }
+
+    // Tell the compositor how the next buffer maps to the surface, takes effect on commit.
+    pub fn apply(&self, surface: &WlSurface, width: u32, height: u32) {
+        match &self.fractional {
+            Some((_, viewport)) => {
+                surface.set_buffer_scale(1);
+                viewport.set_destination(width as i32, height as i32);
+            }
+            None => surface.set_buffer_scale(self.integer_scale),
+        }
+    }
+
+    // Update the integer scale, returns true if it changed what gets rendered.
+    pub fn set_integer_scale(&mut self, scale: i32) -> bool {
+        let before = self.factor();
+        self.integer_scale = scale.max(1);
+        self.factor() != before
+    }
+
+    pub fn set_fractional_scale(&mut self, scale: u32) -> bool {
+        let before = self.factor();
+        self.fractional_scale = Some(scale);
+        self.factor() != before
+    }
+
+    pub fn enter(&mut self, output: &WlOutput) {
+        if !self.entered.contains(output) {
+            self.entered.push(output.clone());
+        }
+    }
+
+    pub fn leave(&mut self, output: &WlOutput) {
+        self.entered.retain(|entered| entered != output);
+    }
+
+    // Scale the surface should use based on the outputs it's shown on, if known.
+    pub fn preferred_output_scale(&self, output_state: &OutputState) -> Option<i32> {
+        self.entered.iter().filter_map(|output| output_state.info(output)).map(|info| info.scale_factor).max()
+    }
+}
+
+impl Drop for ScaleState {
+    fn drop(&mut self) {
+        if let Some((fractional, viewport)) = self.fractional.take() {
+            fractional.destroy();
+            viewport.destroy();
+        }
+    }
+}
+
+impl Dispatch<WpFractionalScaleV1, WlSurface> for HyogenLayer {
+    fn event(
+            state: &mut Self,
+            _proxy: &WpFractionalScaleV1,
+            event: wp_fractional_scale_v1::Event,
+            surface: &WlSurface,
+            _conn: &Connection,
+            qh: &QueueHandle<Self>,
+        ) {
+        if let wp_fractional_scale_v1::Event::PreferredScale { scale } = event {
+            state.fractional_scale_changed(surface, scale, qh);
+        }
+    }
+}
+
+impl Dispatch<WpFractionalScaleManagerV1, ()> for HyogenLayer {
+    fn event(_: &mut Self, _: &WpFractionalScaleManagerV1, _: <WpFractionalScaleManagerV1 as wayland_client::Proxy>::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<WpViewporter, ()> for HyogenLayer {
+    fn event(_: &mut Self, _: &WpViewporter, _: <WpViewporter as wayland_client::Proxy>::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<WpViewport, ()> for HyogenLayer {
+    fn event(_: &mut Self, _: &WpViewport, _: <WpViewport as wayland_client::Proxy>::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {}
+}
@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+// Decides when frames get drawn, so an idle face doesn't render at the full refresh rate.
+pub struct FrameScheduler {
+    min_interval: Option<Duration>,    // Derived from the fps cap
+    last_frame: Option<Instant>,
+    deferred: Option<Instant>,         // Frame held back by the cap, drawn at this time
+    timers: Vec<Instant>,              // Pending wakeups
+}
+
+impl FrameScheduler {
+    pub fn new(fps_cap: Option<f64>) -> Self {
+        let mut scheduler = Self {
+            min_interval: None,
+            last_frame: None,
+            deferred: None,
+            timers: Vec::new(),
+        };
+        scheduler.set_fps_cap(fps_cap);
+        scheduler
+    }
+
+    pub fn set_fps_cap(&mut self, fps_cap: Option<f64>) {
+        // None or a non positive cap disables the limit
+        self.min_interval = fps_cap
+            .filter(|fps| *fps > 0.0 && fps.is_finite())
+            .map(|fps| Duration::from_secs_f64(1.0 / fps));
+    }
+
+    pub fn begin_frame(&mut self, now: Instant) -> bool {
+        // Returns whether a frame may be drawn now, if not it's deferred until the cap allows it
+        if let (Some(min_interval), Some(last_frame)) = (self.min_interval, self.last_frame) {
+            let allowed_at = last_frame + min_interval;
+            if now < allowed_at {
+                self.deferred = Some(allowed_at);
+                return false;
+            }
+        }
+
+        self.last_frame = Some(now);
+        self.deferred = None;
+        true
+    }
+
+    pub fn schedule(&mut self, at: Instant) {
+        // Wake up at the given time, even if no frame callback is pending
+        self.timers.push(at);
+    }
+
+    pub fn next_wakeup(&self) -> Option<Instant> {
+        self.timers.iter().copied().chain(self.deferred).min()
+    }
+
+    pub fn take_due(&mut self, now: Instant) -> bool {
+        // Drop every timer that expired, returns true if any did
+        let pending = self.timers.len();
+        self.timers.retain(|at| *at > now);
+        let mut fired = self.timers.len() != pending;
+
+        if self.deferred.is_some_and(|at| at <= now) {
+            self.deferred = None;
+            fired = true;
+        }
+        fired
+    }
+}
@@ -0,0 +1,175 @@
+use std::{fmt, path::PathBuf, thread, time::Duration};
+
+use smithay_client_toolkit::{compositor::CompositorState, shell::{wlr_layer::LayerShell, xdg::XdgShell}, shm::Shm};
+use wayland_client::{globals::{registry_queue_init, BindError, GlobalError}, ConnectError, Connection};
+
+use crate::{cli::RunArgs, control::stdin::StdinScript, event_loop::HyogenLoop, events::Event, face::FaceState, layer::HyogenLayer, surface::Shell};
+
+#[derive(Debug)]
+pub enum SessionError {
+    Connect(ConnectError),
+    Globals(GlobalError),
+    Bind(&'static str, BindError),
+    EventLoop(calloop::Error),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Connect(err) => write!(f, "Failed to connect to the compositor: {}", err),
+            SessionError::Globals(err) => write!(f, "Failed to get the compositor globals: {}", err),
+            SessionError::Bind(global, err) => write!(f, "{} is not available: {}", global, err),
+            SessionError::EventLoop(err) => write!(f, "Event loop failed: {}", err),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SessionOptions {
+    pub supervise: bool,                    // Reconnect when the compositor goes away
+    pub window: bool,                       // Use an xdg_toplevel window even if layer-shell is available
+    pub control_socket: Option<PathBuf>,    // Where to listen for control commands, needs the ipc feature
+    pub dbus: bool,                         // Offer org.osmos.Hyogen on the session bus, same
+    pub stdin: Option<StdinScript>,         // Commands piped in with --stdin
+}
+
+// Delay between reconnect attempts, doubling every time up to `max`.
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self { initial, max, next: initial }
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.next = self.initial;
+    }
+}
+
+// Connect to the compositor and put the face on it. The face is handed back if that fails.
+pub fn connect(face: FaceState, options: &SessionOptions) -> Result<(HyogenLayer, HyogenLoop), (SessionError, Box<FaceState>)> {
+    // All wayland clients start by connecting to the compositor (Aurora).
+    let conn = match Connection::connect_to_env() {
+        Ok(conn) => conn,
+        Err(err) => return Err((SessionError::Connect(err), Box::new(face))),
+    };
+
+    // Enumerate the list of glovals to get the protocols the server implements.
+    let (globals, event_queue) = match registry_queue_init(&conn) {
+        Ok(globals) => globals,
+        Err(err) => return Err((SessionError::Globals(err), Box::new(face))),
+    };
+    let qh = event_queue.handle();
+
+    // The compositor allows configuring surfaces to be presented.
+    let compositor = match CompositorState::bind(&globals, &qh) {
+        Ok(compositor) => compositor,
+        Err(err) => return Err((SessionError::Bind("wl_compositor", err), Box::new(face))),
+    };
+
+    // HyogenUI uses the wlr layer shell (Aurora has it), other compositors get a regular window.
+    let layer_shell = match options.window {
+        true => None,
+        false => LayerShell::bind(&globals, &qh).ok(),
+    };
+    let shell = match layer_shell {
+        Some(layer_shell) => Shell::Layer(layer_shell),
+        None => match XdgShell::bind(&globals, &qh) {
+            Ok(xdg_shell) => {
+                if !options.window {
+                    tracing::warn!("Layer shell is not available, falling back to a window");
+                }
+                Shell::Xdg(xdg_shell)
+            }
+            Err(err) => return Err((SessionError::Bind("layer shell or xdg shell", err), Box::new(face))),
+        },
+    };
+
+    // Using wl_shm to allow software rendering to a buffer, shared with the compositor process.
+    // TODO: Use GPU for rendering.
+    let shm = match Shm::bind(&globals, &qh) {
+        Ok(shm) => shm,
+        Err(err) => return Err((SessionError::Bind("wl_shm", err), Box::new(face))),
+    };
+
+    let config_path = face.settings.as_ref().and_then(RunArgs::config_path);
+    let hvf_paths: Vec<PathBuf> = match face.config.hvf.is_empty() {
+        true => face.hvf_path.iter().map(PathBuf::from).collect(),
+        false => face.config.hvf.clone(),
+    };
+    let hyogen_layer = HyogenLayer::with_face(compositor, shell, &globals, &qh, shm, face);
+
+    // Wayland, timers and signals all go through one event loop.
+    match HyogenLoop::new(conn, event_queue) {
+        Ok(mut event_loop) => {
+            #[cfg(feature = "ipc")]
+            {
+                if let Some(path) = &options.control_socket {
+                    event_loop.serve_control(path);
+                }
+                if options.dbus {
+                    event_loop.serve_dbus();
+                }
+            }
+            if let Some(script) = &options.stdin {
+                event_loop.serve_stdin(script);
+            }
+            for path in config_path.iter().chain(&hvf_paths) {
+                event_loop.reload_on_change(path);
+            }
+            Ok((hyogen_layer, event_loop))
+        }
+        Err(err) => Err((SessionError::EventLoop(err), Box::new(hyogen_layer.into_face_state()))),
+    }
+}
+
+// Show the face until it exits. When supervising, a lost or refused connection is retried with backoff
+// and the face picks up where it left off.
+pub fn run(face: FaceState, options: SessionOptions) -> Result<(), SessionError> {
+    let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(10));
+    let mut face = face;
+
+    loop {
+        let (mut hyogen_layer, mut event_loop) = match connect(face, &options) {
+            Ok(session) => session,
+            Err((err, returned)) if options.supervise => {
+                let delay = backoff.next_delay();
+                tracing::warn!("{}, retrying in {:?}", err, delay);
+                thread::sleep(delay);
+                face = *returned;
+                continue;
+            }
+            Err((err, _)) => return Err(err),
+        };
+        backoff.reset();
+        hyogen_layer.face.publish(Event::Connected);
+
+        // We don't draw immediately, the configure will notify us when to first draw.
+        let result = event_loop.run(&mut hyogen_layer);
+        drop(event_loop);
+
+        match result {
+            Ok(()) => return Ok(()),
+            Err(_) if hyogen_layer.exiting() => return Ok(()),
+            Err(err) if options.supervise => {
+                tracing::warn!("Lost the connection to the compositor ({}), reconnecting", err);
+                face = hyogen_layer.into_face_state();
+                face.publish(Event::Disconnected);
+
+                // Give the compositor a moment to come back up
+                thread::sleep(backoff.next_delay());
+            }
+            Err(err) => return Err(SessionError::EventLoop(err)),
+        }
+    }
+}
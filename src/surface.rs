@@ -0,0 +1,132 @@
+use smithay_client_toolkit::{
+    compositor::CompositorState, output::OutputState, shell::{wlr_layer::{LayerShell, LayerSurface, SurfaceKind}, xdg::{window::{Window, WindowDecorations}, XdgShell}, WaylandSurface}, shm::{slot::SlotPool, CreatePoolError, Shm}
+};
+use wayland_client::{protocol::{wl_output::WlOutput, wl_surface::WlSurface}, Proxy, QueueHandle};
+
+use crate::{layer::HyogenLayer, placement::Placement, renderer::shm::ShmBackend, scale::{ScaleManagers, ScaleState}};
+
+// How faces get on screen.
+pub enum Shell {
+    Layer(LayerShell),
+    Xdg(XdgShell),      // Regular window, for compositors without wlr-layer-shell
+}
+
+pub enum SurfaceRole {
+    Layer(LayerSurface),
+    Window(Window),
+}
+
+// One surface showing the face, there is one for every output the face is on.
+pub struct FaceSurface {
+    pub scale: ScaleState,          // Declared first so it's destroyed before the surface
+    pub role: SurfaceRole,
+    pub output: Option<WlOutput>,   // None when the compositor picks the output
+    pub backend: ShmBackend,
+
+    pub width: u32,
+    pub height: u32,
+    pub first_configure: bool,
+    pub frame_pending: bool,        // A frame callback was requested and hasn't fired yet
+}
+
+impl FaceSurface {
+    pub fn new(
+        compositor: &CompositorState,
+        shell: &Shell,
+        placement: &Placement,
+        output: Option<WlOutput>,
+        scale_managers: &ScaleManagers,
+        shm: &Shm,
+        qh: &QueueHandle<HyogenLayer>,
+    ) -> Result<Self, CreatePoolError> {
+        // We don't know how large the surface will be yet, so lets assume the size we suggested for the initial memory allocation.
+        let pool = SlotPool::new(800 * 480 * 4, shm)?;
+
+        // A layer surface is created from a surface.
+        let surface = compositor.create_surface(qh);
+        let role = match shell {
+            Shell::Layer(layer_shell) => {
+                let layer = layer_shell.create_layer_surface(qh, surface, placement.layer, Some(placement.namespace.clone()), output.as_ref());
+                placement.apply(&layer);
+                SurfaceRole::Layer(layer)
+            }
+            Shell::Xdg(xdg_shell) => {
+                let window = xdg_shell.create_window(surface, WindowDecorations::ServerDefault, qh);
+                window.set_title("Hyogen UI");
+                window.set_app_id(placement.namespace.clone());
+                SurfaceRole::Window(window)
+            }
+        };
+
+        // In order for layer surface to be mapped, we need to perform an initial commit with no attached buffer
+        // The compositor will respond with an initial configure that we can then use to present to the layer
+        // surface with the correct options.
+        role.wl_surface().commit();
+
+        Ok(Self {
+            scale: ScaleState::new(scale_managers, role.wl_surface(), qh),
+            role,
+            output,
+            backend: ShmBackend::new(pool),
+            width: 800,
+            height: 480,
+            first_configure: true,
+            frame_pending: false,
+        })
+    }
+
+    pub fn wl_surface(&self) -> &WlSurface {
+        self.role.wl_surface()
+    }
+
+    pub fn commit(&self) {
+        self.wl_surface().commit();
+    }
+
+    // Changing layers in place needs zwlr_layer_surface_v1 version 2.
+    pub fn can_change_layer(&self) -> bool {
+        match &self.role {
+            SurfaceRole::Layer(layer) => match layer.kind() {
+                SurfaceKind::Wlr(wlr_surface) => wlr_surface.version() >= 2,
+                _ => false,
+            },
+            SurfaceRole::Window(_) => true,
+        }
+    }
+
+    // Send a changed placement, windows are placed by the compositor and only keep their size.
+    pub fn apply_placement(&self, placement: &Placement, layer_changed: bool) {
+        if let SurfaceRole::Layer(layer) = &self.role {
+            if layer_changed {
+                layer.set_layer(placement.layer);
+            }
+            placement.apply(layer);
+        }
+        self.commit();
+    }
+
+    // Size to use when the compositor leaves it up to us: the requested size, or the whole output when stretched.
+    pub fn fallback_size(&self, placement: &Placement, output_state: &OutputState) -> (u32, u32) {
+        let output_size = self.output.as_ref()
+            .and_then(|output| output_state.info(output))
+            .and_then(|info| info.logical_size)
+            .map(|(width, height)| (width.max(1) as u32, height.max(1) as u32))
+            .unwrap_or((800, 480));
+
+        match placement.size {
+            (0, 0) => output_size,
+            (0, height) => (output_size.0, height),
+            (width, 0) => (width, output_size.1),
+            size => size,
+        }
+    }
+}
+
+impl SurfaceRole {
+    pub fn wl_surface(&self) -> &WlSurface {
+        match self {
+            SurfaceRole::Layer(layer) => layer.wl_surface(),
+            SurfaceRole::Window(window) => window.wl_surface(),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::{fmt::Write as _, io::{self, Write}, str::FromStr, thread, time::{Duration, Instant}};
+
+use crate::{headless::{self, HeadlessError, RenderArgs, RenderOptions}, renderer::{memory::MemoryBackend, Color}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellMode {
+    Braille,       // 2x4 dots per cell, one color per cell
+    HalfBlock,     // 1x2 pixels per cell, each with its own color
+}
+
+impl CellMode {
+    // Pixels covered by a single terminal cell.
+    pub fn cell_size(self) -> (u32, u32) {
+        match self {
+            CellMode::Braille => (2, 4),
+            CellMode::HalfBlock => (1, 2),
+        }
+    }
+}
+
+impl FromStr for CellMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "braille" => Ok(CellMode::Braille),
+            "half" | "half-block" => Ok(CellMode::HalfBlock),
+            _ => Err(format!("Unknown cell mode {}, expected braille or half", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PreviewOptions {
+    pub render: RenderOptions,    // Its size is in pixels, derived from the cell grid
+    pub mode: CellMode,
+    pub columns: u32,
+    pub rows: u32,
+    pub repeat: bool,             // Play the timeline over and over
+}
+
+impl PreviewOptions {
+    // Parse the arguments following the `preview` subcommand.
+    pub fn from_args(args: &[String]) -> Result<Self, HeadlessError> {
+        let mut render = RenderArgs::default();
+        let mut mode = CellMode::HalfBlock;
+        let mut cells = None;
+        let mut fps = 30.0;
+        let mut repeat = false;
+
+        let mut iter = args.iter();
+        while let Some(flag) = iter.next() {
+            let mut value = || iter.next().ok_or_else(|| HeadlessError::InvalidArgument(format!("{} expects a value", flag)));
+
+            match flag.as_str() {
+                "--mode" => mode = value()?.parse().map_err(HeadlessError::InvalidArgument)?,
+                "--cells" => cells = Some(headless::parse_size(value()?)?),
+                "--fps" => fps = value()?.parse().map_err(|_| HeadlessError::InvalidArgument(format!("Invalid value for {}", flag)))?,
+                "--loop" => repeat = true,
+                _ => {
+                    if !render.apply(flag, &mut iter)? {
+                        return Err(HeadlessError::InvalidArgument(format!("Unknown option {}", flag)));
+                    }
+                }
+            }
+        }
+
+        let mut render = render.finish()?;
+        if fps <= 0.0 || !f64::is_finite(fps) {
+            return Err(HeadlessError::InvalidArgument("--fps must be positive".to_string()));
+        }
+        render.fps = fps;
+
+        // Fill the terminal by default, keeping a line for the shell prompt
+        let (columns, rows) = cells.unwrap_or_else(|| {
+            let (columns, rows) = terminal_size();
+            (columns, rows.saturating_sub(1).max(1))
+        });
+
+        let (cell_width, cell_height) = mode.cell_size();
+        render.width = columns * cell_width;
+        render.height = rows * cell_height;
+
+        Ok(Self { render, mode, columns, rows, repeat })
+    }
+}
+
+// Size of the terminal on stdout in cells, 80x24 if it isn't one.
+pub fn terminal_size() -> (u32, u32) {
+    match rustix::termios::tcgetwinsize(io::stdout()) {
+        Ok(size) if size.ws_col > 0 && size.ws_row > 0 => (size.ws_col as u32, size.ws_row as u32),
+        _ => (80, 24),
+    }
+}
+
+// Turn a rendered frame into lines of colored terminal cells.
+pub fn encode_frame(backend: &MemoryBackend, mode: CellMode, background: Color) -> String {
+    let (cell_width, cell_height) = mode.cell_size();
+    let columns = backend.width() / cell_width;
+    let rows = backend.height() / cell_height;
+
+    let mut out = String::new();
+    for row in 0..rows {
+        let mut colors = CellColors::default();
+        for column in 0..columns {
+            let (x, y) = (column * cell_width, row * cell_height);
+            match mode {
+                CellMode::HalfBlock => {
+                    let top = backend.pixel(x, y);
+                    let bottom = backend.pixel(x, y + 1);
+                    match (top.a > 0, bottom.a > 0) {
+                        (false, false) => colors.write(&mut out, None, None, ' '),
+                        (true, false) => colors.write(&mut out, Some(top), None, '▀'),
+                        (false, true) => colors.write(&mut out, Some(bottom), None, '▄'),
+                        (true, true) => colors.write(&mut out, Some(top), Some(bottom), '▀'),
+                    }
+                }
+                CellMode::Braille => {
+                    let (dots, color) = braille_cell(backend, x, y, background);
+                    let background = (background.a > 0).then_some(background);
+                    colors.write(&mut out, color, background, char::from_u32(0x2800 + dots as u32).unwrap_or(' '));
+                }
+            }
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+// Dot pattern of the 2x4 braille cell at (x, y) and the average color of the raised dots.
+fn braille_cell(backend: &MemoryBackend, x: u32, y: u32, background: Color) -> (u8, Option<Color>) {
+    // Bit of every dot, by row then column
+    const DOTS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+    let mut dots = 0;
+    let (mut r, mut g, mut b, mut count) = (0u32, 0u32, 0u32, 0u32);
+    for (dy, bits) in DOTS.iter().enumerate() {
+        for (dx, bit) in bits.iter().enumerate() {
+            let pixel = backend.pixel(x + dx as u32, y + dy as u32);
+            if pixel.a == 0 || pixel == background {
+                continue;
+            }
+            dots |= bit;
+            r += pixel.r as u32;
+            g += pixel.g as u32;
+            b += pixel.b as u32;
+            count += 1;
+        }
+    }
+
+    let color = (count > 0).then(|| Color::rgba((r / count) as u8, (g / count) as u8, (b / count) as u8, 255));
+    (dots, color)
+}
+
+// Emits ANSI truecolor codes only when the colors change from the previous cell.
+#[derive(Default)]
+struct CellColors {
+    foreground: Option<Option<Color>>,
+    background: Option<Option<Color>>,
+}
+
+impl CellColors {
+    fn write(&mut self, out: &mut String, foreground: Option<Color>, background: Option<Color>, cell: char) {
+        if self.foreground != Some(foreground) {
+            match foreground {
+                Some(color) => { let _ = write!(out, "\x1b[38;2;{};{};{}m", color.r, color.g, color.b); }
+                None => out.push_str("\x1b[39m"),
+            }
+            self.foreground = Some(foreground);
+        }
+        if self.background != Some(background) {
+            match background {
+                Some(color) => { let _ = write!(out, "\x1b[48;2;{};{};{}m", color.r, color.g, color.b); }
+                None => out.push_str("\x1b[49m"),
+            }
+            self.background = Some(background);
+        }
+        out.push(cell);
+    }
+}
+
+// Shows the cursor again and resets the colors, however the preview ends.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout().lock();
+        let _ = write!(stdout, "\x1b[0m\x1b[?25h");
+        let _ = stdout.flush();
+    }
+}
+
+// Play the timeline in place on stdout, in real time at the requested frame rate. `wait` sleeps until the
+// next frame is due and returns false to stop early, e.g. on Ctrl-C.
+pub fn preview(options: &PreviewOptions, mut wait: impl FnMut(Duration) -> bool) -> Result<usize, HeadlessError> {
+    // Encoded once, looping replays the same frames
+    let mut frames = Vec::with_capacity(headless::frame_count(&options.render));
+    headless::render_frames(&options.render, |_, backend| {
+        frames.push(encode_frame(backend, options.mode, options.render.background));
+        Ok(())
+    })?;
+
+    let frame_interval = Duration::from_secs_f64(1.0 / options.render.fps);
+    let _guard = TerminalGuard;
+    let mut stdout = io::stdout().lock();
+
+    // Hide the cursor while animating
+    write!(stdout, "\x1b[?25l")?;
+
+    let mut played = 0;
+    loop {
+        let start = Instant::now();
+        for (index, frame) in frames.iter().enumerate() {
+            // Move back up over the previous frame to draw over it
+            if played > 0 {
+                write!(stdout, "\x1b[{}A", options.rows)?;
+            }
+            stdout.write_all(frame.as_bytes())?;
+            stdout.flush()?;
+            played += 1;
+
+            let due = start + frame_interval * (index as u32 + 1);
+            if !wait(due.saturating_duration_since(Instant::now())) {
+                return Ok(played);
+            }
+        }
+        if !options.repeat {
+            return Ok(played);
+        }
+    }
+}
+
+// Plain sleeping between frames, for callers without signal handling of their own.
+pub fn sleep(duration: Duration) -> bool {
+    thread::sleep(duration);
+    true
+}
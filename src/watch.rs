@@ -0,0 +1,39 @@
+use std::{
+    ffi::OsStr, io::{self, ErrorKind}, mem::MaybeUninit, os::unix::ffi::OsStrExt, path::Path
+};
+
+use calloop::{generic::Generic, Interest, LoopHandle, Mode, PostAction, RegistrationToken};
+use rustix::{fs::inotify::{self, CreateFlags, WatchFlags}, io::Errno};
+
+// Call back whenever a file is written or replaced. Its directory is watched rather than the file itself,
+// as editors tend to write a new file and rename it over the old one.
+pub fn watch_file<D: 'static>(path: &Path, handle: &LoopHandle<'static, D>, mut changed: impl FnMut(&mut D) + 'static) -> io::Result<RegistrationToken> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path.file_name().ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, format!("{} is not a file", path.display())))?.to_os_string();
+
+    let fd = inotify::init(CreateFlags::CLOEXEC | CreateFlags::NONBLOCK)?;
+    inotify::add_watch(&fd, dir, WatchFlags::CLOSE_WRITE | WatchFlags::MOVED_TO)?;
+
+    let source = Generic::new(fd, Interest::READ, Mode::Level);
+    handle.insert_source(source, move |_, fd, state| {
+        // Editors write in several steps, call back once for everything that's queued up
+        let mut buffer = [MaybeUninit::uninit(); 4096];
+        let mut reader = inotify::Reader::new(&**fd, &mut buffer);
+        let mut touched = false;
+        loop {
+            match reader.next() {
+                Ok(event) => touched |= event.file_name().is_some_and(|file| OsStr::from_bytes(file.to_bytes()) == name),
+                Err(Errno::INTR) => continue,
+                Err(_) => break,
+            }
+        }
+
+        if touched {
+            changed(state);
+        }
+        Ok(PostAction::Continue)
+    }).map_err(|err| io::Error::other(err.error))
+}
@@ -0,0 +1,32 @@
+use smithay_client_toolkit::{delegate_xdg_shell, delegate_xdg_window, shell::{xdg::window::{Window, WindowConfigure, WindowHandler}, WaylandSurface}};
+use wayland_client::QueueHandle;
+
+use crate::layer::HyogenLayer;
+
+// The xdg_toplevel fallback, faces shown as regular windows for compositors without wlr-layer-shell.
+impl WindowHandler for HyogenLayer {
+    fn request_close(&mut self, _conn: &wayland_client::Connection, qh: &QueueHandle<Self>, _window: &Window) {
+        self.begin_exit(qh);
+    }
+
+    fn configure(
+            &mut self,
+            _conn: &wayland_client::Connection,
+            qh: &QueueHandle<Self>,
+            window: &Window,
+            configure: WindowConfigure,
+            _serial: u32,
+        ) {
+        // No size means we get to choose, same as a 0 from the layer shell
+        let new_size = (
+            configure.new_size.0.map_or(0, |width| width.get()),
+            configure.new_size.1.map_or(0, |height| height.get()),
+        );
+
+        if let Some(index) = self.surface_index(window.wl_surface()) {
+            self.configure_surface(index, new_size, qh);
+        }
+    }
+}
+delegate_xdg_shell!(HyogenLayer);
+delegate_xdg_window!(HyogenLayer);
@@ -0,0 +1,43 @@
+// The face without a compositor, for the tests built with the wayland feature.
+use std::{fs, path::Path, time::Instant};
+
+use hyogen_ui::{
+    control::{protocol::Command, ControlHandler},
+    events::EventBus,
+    face::FaceState,
+    hvf::loader::HVFLoader,
+    placement::Placement,
+};
+use serde_json::Value;
+
+// The face on its own, as the control interfaces see it without a compositor.
+pub struct TestFace {
+    pub face: FaceState,
+}
+
+impl ControlHandler for TestFace {
+    fn handle_command(&mut self, command: Command) -> Result<Value, String> {
+        self.face.apply(command, Instant::now())
+    }
+
+    fn events(&mut self) -> &mut EventBus {
+        &mut self.face.events
+    }
+}
+
+// A square in a few sizes, enough to tell expressions apart.
+pub fn square(size: f64) -> String {
+    format!("[[[0.0, 0.0], [{size}, 0.0], [{size}, {size}], [0.0, {size}]]]")
+}
+
+// A face with neutral, happy and sad squares of size 10, 20 and 30.
+pub fn test_face(dir: &Path) -> TestFace {
+    let path = dir.join("face.hvf");
+    let hvf = format!(r#"{{"expression": {{"neutral": {}, "happy": {}, "sad": {}}}}}"#, square(10.0), square(20.0), square(30.0));
+    fs::write(&path, hvf).unwrap();
+
+    let loader = HVFLoader::new(path.to_str().unwrap()).unwrap();
+    let mut face = FaceState::new(loader.get("expression", "neutral").unwrap(), Placement::default());
+    face.hvf = Some(loader);
+    TestFace { face }
+}
@@ -0,0 +1,16 @@
+// Helpers shared by the integration tests, not every test uses all of them.
+#![allow(dead_code, unused_imports)]
+
+use std::{env, fs, path::PathBuf};
+
+// The face helpers need the wayland feature, the rest is usable by every test.
+#[cfg(feature = "wayland")]
+mod face;
+#[cfg(feature = "wayland")]
+pub use face::*;
+
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("hyogen-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
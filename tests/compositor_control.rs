@@ -0,0 +1,290 @@
+mod common;
+
+use std::{
+    fs, os::unix::net::UnixStream, sync::{mpsc, Arc, Mutex}, thread, time::{Duration, Instant}
+};
+
+use common::{scratch_dir, test_face, TestFace};
+use hyogen_ui::{
+    animator::animator::Animator,
+    compositor_control::{CompositorControl, CompositorControlHandler},
+    control::{protocol::{Command, Request}, ControlHandler},
+    events::EventBus,
+    delegate_hyogen_control,
+};
+use serde_json::Value;
+use wayland_client::{globals::{registry_queue_init, GlobalListContents}, protocol::wl_registry, Connection, EventQueue, QueueHandle};
+use wayland_server::{backend::ClientData, Display, GlobalDispatch};
+
+// Compositor side of the protocol, only the tests need it.
+#[allow(non_upper_case_globals, clippy::all)]
+mod server {
+    use wayland_server;
+
+    pub mod __interfaces {
+        use wayland_server::backend as wayland_backend;
+        wayland_scanner::generate_interfaces!("protocols/hyogen-control-v1.xml");
+    }
+    use self::__interfaces::*;
+
+    wayland_scanner::generate_server_code!("protocols/hyogen-control-v1.xml");
+}
+
+use server::hyogen_control_v1::{self, HyogenControlV1};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Answer {
+    Finished(u32),
+    Rejected(u32, String),
+}
+
+// Just enough of Aurora to advertise hyogen_control_v1 and note what the face answers.
+struct Aurora {
+    control: Option<HyogenControlV1>,
+    answers: Arc<Mutex<Vec<Answer>>>,
+}
+
+struct AuroraClient;
+impl ClientData for AuroraClient {}
+
+impl GlobalDispatch<HyogenControlV1, ()> for Aurora {
+    fn bind(
+        state: &mut Self,
+        _handle: &wayland_server::DisplayHandle,
+        _client: &wayland_server::Client,
+        resource: wayland_server::New<HyogenControlV1>,
+        _global_data: &(),
+        data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        state.control = Some(data_init.init(resource, ()));
+    }
+}
+
+impl wayland_server::Dispatch<HyogenControlV1, ()> for Aurora {
+    fn request(
+        state: &mut Self,
+        _client: &wayland_server::Client,
+        _resource: &HyogenControlV1,
+        request: hyogen_control_v1::Request,
+        _data: &(),
+        _dhandle: &wayland_server::DisplayHandle,
+        _data_init: &mut wayland_server::DataInit<'_, Self>,
+    ) {
+        let answer = match request {
+            hyogen_control_v1::Request::Finished { serial } => Answer::Finished(serial),
+            hyogen_control_v1::Request::Rejected { serial, reason } => Answer::Rejected(serial, reason),
+            hyogen_control_v1::Request::Destroy => return,
+        };
+        state.answers.lock().unwrap().push(answer);
+    }
+}
+
+type Send = Box<dyn FnOnce(&HyogenControlV1) + std::marker::Send>;
+
+// Runs the compositor stand-in on its own thread, so the face can do blocking roundtrips against it.
+struct Compositor {
+    commands: Option<mpsc::Sender<(Send, mpsc::Sender<()>)>>,
+    answers: Arc<Mutex<Vec<Answer>>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Compositor {
+    fn start(stream: UnixStream) -> Self {
+        let answers = Arc::new(Mutex::new(Vec::new()));
+        let (commands, received) = mpsc::channel::<(Send, mpsc::Sender<()>)>();
+
+        let recorded = answers.clone();
+        let thread = thread::spawn(move || {
+            let mut display: Display<Aurora> = Display::new().unwrap();
+            display.handle().create_global::<Aurora, HyogenControlV1, ()>(1, ());
+            display.handle().insert_client(stream, Arc::new(AuroraClient)).unwrap();
+            let mut aurora = Aurora { control: None, answers: recorded };
+
+            loop {
+                match received.try_recv() {
+                    Ok((send, sent)) => {
+                        send(aurora.control.as_ref().expect("the face didn't bind hyogen_control_v1"));
+                        display.flush_clients().unwrap();
+                        let _ = sent.send(());
+                    }
+                    Err(mpsc::TryRecvError::Disconnected) => break,
+                    Err(mpsc::TryRecvError::Empty) => {}
+                }
+
+                display.dispatch_clients(&mut aurora).unwrap();
+                display.flush_clients().unwrap();
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+
+        Self { commands: Some(commands), answers, thread: Some(thread) }
+    }
+
+    // Send an event to the face, returns once it's on the socket.
+    fn send(&self, send: impl FnOnce(&HyogenControlV1) + std::marker::Send + 'static) {
+        let (sent, done) = mpsc::channel();
+        self.commands.as_ref().unwrap().send((Box::new(send), sent)).unwrap();
+        done.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+
+    fn answers(&self) -> Vec<Answer> {
+        self.answers.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Compositor {
+    fn drop(&mut self) {
+        self.commands.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// The client side, as HyogenLayer has it but without any surfaces.
+struct Face {
+    test: TestFace,
+    control: CompositorControl,
+}
+
+impl ControlHandler for Face {
+    fn handle_command(&mut self, command: Command) -> Result<Value, String> {
+        self.test.handle_command(command)
+    }
+
+    fn events(&mut self) -> &mut EventBus {
+        self.test.events()
+    }
+}
+
+impl CompositorControlHandler for Face {
+    fn compositor_control(&mut self) -> &mut CompositorControl {
+        &mut self.control
+    }
+
+    fn animator(&self) -> &Animator {
+        &self.test.face.animator
+    }
+}
+delegate_hyogen_control!(Face);
+
+impl wayland_client::Dispatch<wl_registry::WlRegistry, GlobalListContents> for Face {
+    fn event(_: &mut Self, _: &wl_registry::WlRegistry, _: wl_registry::Event, _: &GlobalListContents, _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+fn connect(name: &str) -> (Compositor, Face, EventQueue<Face>) {
+    let (client, server) = UnixStream::pair().unwrap();
+    let compositor = Compositor::start(server);
+
+    let connection = Connection::from_socket(client).unwrap();
+    let (globals, event_queue) = registry_queue_init::<Face>(&connection).unwrap();
+    let control = CompositorControl::bind(&globals, &event_queue.handle());
+    assert!(control.is_bound());
+
+    let dir = scratch_dir(name);
+    let face = Face { test: test_face(&dir), control };
+    fs::remove_dir_all(&dir).unwrap();
+    (compositor, face, event_queue)
+}
+
+// Let the face handle everything sent so far and let the compositor see its answers.
+fn sync(face: &mut Face, event_queue: &mut EventQueue<Face>) {
+    event_queue.roundtrip(face).unwrap();
+    face.control.poll(&face.test.face.animator, Instant::now());
+    event_queue.roundtrip(face).unwrap();
+}
+
+#[test]
+fn expressions_are_set_and_finished() {
+    let (compositor, mut face, mut event_queue) = connect("wayland-set");
+    event_queue.roundtrip(&mut face).unwrap();
+
+    compositor.send(|control| control.set_expression(1, "happy".to_string(), 0, "out-cubic".to_string()));
+    sync(&mut face, &mut event_queue);
+    assert_eq!(face.test.face.expression, "happy");
+    assert_eq!(compositor.answers(), [Answer::Finished(1)]);
+
+    // A long transition is only finished once the face settled
+    compositor.send(|control| control.set_expression(2, "sad".to_string(), 200, String::new()));
+    compositor.send(|control| control.enqueue_expression(3, "neutral".to_string(), 0, "linear".to_string()));
+    sync(&mut face, &mut event_queue);
+    assert_eq!(face.test.face.expression, "neutral");
+    assert_eq!(compositor.answers().len(), 1);
+
+    let settles_at = face.control.next_wakeup(&face.test.face.animator).unwrap();
+    face.control.poll(&face.test.face.animator, settles_at);
+    event_queue.roundtrip(&mut face).unwrap();
+    assert_eq!(compositor.answers()[1..], [Answer::Finished(2), Answer::Finished(3)]);
+}
+
+#[test]
+fn interrupted_commands_are_never_finished() {
+    let (compositor, mut face, mut event_queue) = connect("wayland-interrupt");
+    event_queue.roundtrip(&mut face).unwrap();
+
+    compositor.send(|control| control.set_expression(1, "happy".to_string(), 10_000, String::new()));
+    compositor.send(|control| control.set_expression(2, "sad".to_string(), 0, String::new()));
+    sync(&mut face, &mut event_queue);
+    assert_eq!(face.test.face.expression, "sad");
+    assert_eq!(compositor.answers(), [Answer::Finished(2)]);
+}
+
+#[test]
+fn commands_from_elsewhere_interrupt_too() {
+    let (compositor, mut face, mut event_queue) = connect("wayland-interrupt-elsewhere");
+    event_queue.roundtrip(&mut face).unwrap();
+
+    compositor.send(|control| control.set_expression(1, "happy".to_string(), 10_000, String::new()));
+    compositor.send(|control| control.enqueue_expression(2, "sad".to_string(), 0, String::new()));
+    sync(&mut face, &mut event_queue);
+
+    // Over the control socket, D-Bus or stdin
+    for line in [r#"{"cmd": "set", "expression": "neutral", "duration": 0}"#, r#"{"cmd": "sequence", "steps": [{"expression": "sad", "duration": 0}], "replace": true}"#] {
+        face.handle_command(Request::parse(line).unwrap().command).unwrap();
+    }
+    sync(&mut face, &mut event_queue);
+    assert_eq!(face.test.face.expression, "sad");
+    assert_eq!(compositor.answers(), []);
+
+    // Later commands are finished as usual
+    compositor.send(|control| control.set_expression(3, "happy".to_string(), 0, String::new()));
+    sync(&mut face, &mut event_queue);
+    assert_eq!(compositor.answers(), [Answer::Finished(3)]);
+}
+
+#[test]
+fn pause_and_resume_answer_right_away() {
+    let (compositor, mut face, mut event_queue) = connect("wayland-pause");
+    event_queue.roundtrip(&mut face).unwrap();
+
+    compositor.send(|control| control.pause(1));
+    event_queue.roundtrip(&mut face).unwrap();
+    assert!(face.test.face.animator.is_paused());
+
+    compositor.send(|control| control.resume(2));
+    event_queue.roundtrip(&mut face).unwrap();
+    event_queue.roundtrip(&mut face).unwrap();
+    assert!(!face.test.face.animator.is_paused());
+    assert_eq!(compositor.answers(), [Answer::Finished(1), Answer::Finished(2)]);
+}
+
+#[test]
+fn invalid_commands_are_rejected() {
+    let (compositor, mut face, mut event_queue) = connect("wayland-rejected");
+    event_queue.roundtrip(&mut face).unwrap();
+
+    compositor.send(|control| control.set_expression(1, "angry".to_string(), 0, String::new()));
+    compositor.send(|control| control.set_expression(2, "happy".to_string(), 0, "bouncy".to_string()));
+    compositor.send(|control| control.blink(3));
+    compositor.send(|control| control.set_expression(4, "happy".to_string(), u32::MAX, String::new()));
+    sync(&mut face, &mut event_queue);
+
+    let answers = compositor.answers();
+    assert!(matches!(&answers[0], Answer::Rejected(1, reason) if reason.contains("angry")), "{:?}", answers);
+    assert!(matches!(&answers[1], Answer::Rejected(2, _)), "{:?}", answers);
+    // The test face has no blink configured
+    assert!(matches!(&answers[2], Answer::Rejected(3, _)), "{:?}", answers);
+    // Longer than a day, like anywhere else
+    assert!(matches!(&answers[3], Answer::Rejected(4, reason) if reason.contains("duration")), "{:?}", answers);
+    assert_eq!(face.test.face.expression, "neutral");
+}
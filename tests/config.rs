@@ -0,0 +1,130 @@
+mod common;
+
+use std::{fs, path::PathBuf, time::Duration};
+
+use common::scratch_dir;
+use hyogen_ui::{
+    cli::{self, Cli},
+    config::{Config, ConfigError},
+    renderer::{Color, Outline},
+};
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(str::to_string).collect()
+}
+
+#[test]
+fn command_line_overrides_config_overrides_defaults() {
+    let dir = scratch_dir("config");
+    let path = dir.join("config.toml");
+    fs::write(&path, r#"
+[face]
+hvf = ["faces/main.hvf", "/usr/share/hyogen/face.hvf"]
+expression = "happy"
+
+[placement]
+layer = "top"
+margin = 10
+
+[blink]
+interval = "2s"
+close = 0.05
+
+[theme]
+outline = "stroke"
+background = "101010FF"
+
+[render]
+fps-cap = 30
+"#).unwrap();
+
+    let Cli::Run(run) = cli::parse(&args(&format!("run --config {} --fps-cap 12 --no-blink", path.display()))).unwrap() else {
+        panic!("Expected the run command");
+    };
+    let config = run.resolve().unwrap();
+    let defaults = Config::default();
+
+    // Relative paths are relative to the config file
+    assert_eq!(config.hvf, [dir.join("faces/main.hvf"), PathBuf::from("/usr/share/hyogen/face.hvf")]);
+    assert_eq!(config.expression, "happy");
+    assert_eq!(config.placement.layer, Layer::Top);
+    assert_eq!(config.placement.margin.top, 10);
+    assert_eq!(config.placement.size, defaults.placement.size);
+    assert_eq!(config.blink.interval, Duration::from_secs(2));
+    assert_eq!(config.blink.close, Duration::from_millis(50));
+    assert_eq!(config.blink.open, defaults.blink.open);
+    assert_eq!(config.theme.background, Color::from_hex("101010FF").unwrap());
+    assert_eq!(config.theme.style.outline, Outline::Stroke);
+    assert_eq!(defaults.theme.style.outline, Outline::Points);
+    assert_eq!(config.fps_cap, Some(12.0));
+    assert!(!config.blink.enabled);
+
+    // A file on the command line replaces the configured ones
+    let Cli::Run(run) = cli::parse(&args(&format!("other.hvf --config {}", path.display()))).unwrap() else {
+        panic!("Expected the run command");
+    };
+    assert_eq!(run.resolve().unwrap().hvf, [PathBuf::from("other.hvf")]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn errors_name_the_key() {
+    let dir = scratch_dir("config-errors");
+    let path = dir.join("config.toml");
+
+    for (toml, key) in [
+        ("[placement]\nlayer = \"middle\"", "placement.layer"),
+        ("[placement]\nlayr = \"top\"", "placement.layr"),
+        ("[placement]\nsize = \"0x100\"\nanchor = \"top\"", "placement.size"),
+        ("[blink]\ninterval = \"soon\"", "blink.interval"),
+        ("[blink]\ninterval = 1e30", "blink.interval"),
+        ("[blink]\nclose = \"1e30s\"", "blink.close"),
+        ("[theme]\nstroke-width = \"thick\"", "theme.stroke-width"),
+        ("[theme]\noutline = \"dotted\"", "theme.outline"),
+        ("[ipc]\ndbus = \"yes\"", "ipc.dbus"),
+        ("[face]\nhvf = [1, 2]", "face.hvf"),
+        ("render = 30", "render"),
+        ("[sound]\nvolume = 1", "sound"),
+    ] {
+        fs::write(&path, toml).unwrap();
+        match Config::load(&path) {
+            Err(ConfigError::Invalid { key: found, .. }) => assert_eq!(found, key, "{}", toml),
+            other => panic!("{} gave {:?}", toml, other),
+        }
+    }
+
+    fs::write(&path, "[placement\nlayer = \"top\"").unwrap();
+    assert!(matches!(Config::load(&path), Err(ConfigError::Parse(..))));
+
+    // Flags on top of the file are named as flags
+    fs::write(&path, "[placement]\nsize = \"0x100\"\nanchor = \"top,left,right\"").unwrap();
+    for (flags, key) in [
+        ("--anchor top", "--anchor"),
+        ("--size 0x0", "--size"),
+        ("--size 0x100 --anchor left", "--size"),
+    ] {
+        let Cli::Run(run) = cli::parse(&args(&format!("--config {} {}", path.display(), flags))).unwrap() else {
+            panic!("Expected the run command");
+        };
+        match run.resolve() {
+            Err(ConfigError::Invalid { key: found, .. }) => assert_eq!(found, key, "{}", flags),
+            other => panic!("{} gave {:?}", flags, other),
+        }
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn command_line_mistakes_are_reported() {
+    assert!(cli::parse(&args("face.hvf --bogus 1")).unwrap_err().contains("--bogus"));
+    assert!(cli::parse(&args("face.hvf --layer middle")).unwrap_err().starts_with("--layer: "));
+    assert!(cli::parse(&args("face.hvf --background purple")).unwrap_err().starts_with("--background: "));
+    assert!(cli::parse(&args("face.hvf --fps-cap")).is_err());
+    assert!(cli::parse(&args("face.hvf other.hvf")).is_err());
+    assert_eq!(cli::parse(&args("help run")).unwrap(), Cli::Help(cli::RUN_USAGE));
+    assert_eq!(cli::parse(&args("export --help")).unwrap(), Cli::Help(cli::EXPORT_USAGE));
+    assert!(matches!(cli::parse(&args("render --hvf face.hvf --to happy")).unwrap(), Cli::Render(args) if args.len() == 4));
+}
@@ -0,0 +1,257 @@
+mod common;
+
+use std::{
+    fs, io::{ErrorKind, Read, Write}, os::unix::{fs::PermissionsExt, net::UnixStream}, path::Path, time::{Duration, Instant}
+};
+
+use calloop::EventLoop;
+use common::{scratch_dir, test_face, TestFace};
+use hyogen_ui::{
+    animator::animator::Animator,
+    control::{protocol::Request, ControlHandler, ControlServer},
+    events::Event,
+    geometry::Point,
+    hvf::loader::HVFLoader,
+};
+use serde_json::Value;
+
+struct Client {
+    stream: UnixStream,
+    buffer: Vec<u8>,
+}
+
+impl Client {
+    fn connect(path: &Path) -> Self {
+        let stream = UnixStream::connect(path).unwrap();
+        stream.set_nonblocking(true).unwrap();
+        Self { stream, buffer: Vec::new() }
+    }
+
+    fn send(&mut self, line: &str) {
+        self.stream.write_all(format!("{}\n", line).as_bytes()).unwrap();
+    }
+
+    // Run the loop until the server wrote a whole line to us.
+    fn receive(&mut self, event_loop: &mut EventLoop<'static, TestFace>, state: &mut TestFace) -> Value {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                return serde_json::from_slice(&line).unwrap();
+            }
+            assert!(Instant::now() < deadline, "no response from the control socket");
+
+            event_loop.dispatch(Some(Duration::from_millis(10)), state).unwrap();
+            let mut chunk = [0u8; 4096];
+            match self.stream.read(&mut chunk) {
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(err) => panic!("{}", err),
+            }
+        }
+    }
+
+    // Run the loop and read until the server closes the connection.
+    fn receive_all(&mut self, event_loop: &mut EventLoop<'static, TestFace>, state: &mut TestFace) -> Vec<u8> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            assert!(Instant::now() < deadline, "the control socket wasn't closed");
+            event_loop.dispatch(Some(Duration::from_millis(1)), state).unwrap();
+            let mut chunk = [0u8; 65536];
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return std::mem::take(&mut self.buffer),
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(err) => panic!("{}", err),
+            }
+        }
+    }
+
+    fn request(&mut self, line: &str, event_loop: &mut EventLoop<'static, TestFace>, state: &mut TestFace) -> Value {
+        self.send(line);
+        self.receive(event_loop, state)
+    }
+}
+
+#[test]
+fn commands_are_answered() {
+    let dir = scratch_dir("commands");
+    let socket = dir.join("control.sock");
+    let mut state = test_face(&dir);
+    let mut event_loop: EventLoop<'static, TestFace> = EventLoop::try_new().unwrap();
+    let _server = ControlServer::bind(&socket, &event_loop.handle()).unwrap();
+
+    let mut client = Client::connect(&socket);
+
+    let response = client.request(r#"{"cmd": "list", "id": 1}"#, &mut event_loop, &mut state);
+    assert_eq!(response["ok"].as_bool(), Some(true));
+    assert_eq!(response["id"].as_u64(), Some(1));
+    assert_eq!(response["expressions"], serde_json::from_str::<Value>(r#"["happy", "neutral", "sad"]"#).unwrap());
+
+    let response = client.request(r#"{"cmd": "set", "expression": "happy", "duration": "1s", "easing": "out-cubic"}"#, &mut event_loop, &mut state);
+    assert_eq!(response["ok"].as_bool(), Some(true));
+
+    let response = client.request(r#"{"cmd": "state"}"#, &mut event_loop, &mut state);
+    assert_eq!(response["state"]["expression"].as_str(), Some("happy"));
+    assert_eq!(response["state"]["animating"].as_bool(), Some(true));
+
+    let response = client.request(r#"{"cmd": "sequence", "steps": [{"expression": "sad"}, {"expression": "neutral", "duration": 0.5}]}"#, &mut event_loop, &mut state);
+    assert_eq!(response["ok"].as_bool(), Some(true));
+    let response = client.request(r#"{"cmd": "state"}"#, &mut event_loop, &mut state);
+    assert_eq!(response["state"]["expression"].as_str(), Some("neutral"));
+    assert_eq!(response["state"]["queued"].as_u64(), Some(2));
+
+    let response = client.request(r#"{"cmd": "pause"}"#, &mut event_loop, &mut state);
+    assert_eq!(response["ok"].as_bool(), Some(true));
+    let response = client.request(r#"{"cmd": "state"}"#, &mut event_loop, &mut state);
+    assert_eq!(response["state"]["paused"].as_bool(), Some(true));
+    client.request(r#"{"cmd": "resume"}"#, &mut event_loop, &mut state);
+
+    let response = client.request(r#"{"cmd": "blend", "weights": {"happy": 1.0, "sad": 1.0}, "duration": 0}"#, &mut event_loop, &mut state);
+    assert_eq!(response["ok"].as_bool(), Some(true));
+    let path = state.face.animator.get_path(Instant::now() + Duration::from_millis(1));
+    assert_eq!(path[0][2], Point::new(25.0, 25.0));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn invalid_commands_are_rejected() {
+    let dir = scratch_dir("invalid");
+    let socket = dir.join("control.sock");
+    let mut state = test_face(&dir);
+    let mut event_loop: EventLoop<'static, TestFace> = EventLoop::try_new().unwrap();
+    let _server = ControlServer::bind(&socket, &event_loop.handle()).unwrap();
+
+    let mut client = Client::connect(&socket);
+    for line in [
+        "not json",
+        r#"{"cmd": "dance"}"#,
+        r#"{"cmd": "set", "expression": "angry", "id": "x"}"#,
+        r#"{"cmd": "set", "expression": "happy", "easing": "bouncy"}"#,
+        r#"{"cmd": "set", "expression": "happy", "duration": -1}"#,
+        r#"{"cmd": "set", "expression": "happy", "duration": 1e20}"#,
+        r#"{"cmd": "set", "expression": "happy", "duration": "1e30s"}"#,
+    ] {
+        let response = client.request(line, &mut event_loop, &mut state);
+        assert_eq!(response["ok"].as_bool(), Some(false), "{} was accepted", line);
+        assert!(response["error"].is_string());
+    }
+
+    // Errors carry the id of the request, as far as it can be made out
+    for (line, id) in [
+        (r#"{"cmd": "set", "expression": "angry", "id": "x"}"#, Some(Value::from("x"))),
+        (r#"{"cmd": "dance", "id": 3}"#, Some(Value::from(3))),
+        (r#"{"cmd": "set", "id": 7, "expression": }"#, Some(Value::from(7))),
+        (r#"{"id" : "a\"b}", "cmd": "set""#, Some(Value::from("a\"b}"))),
+        (r#"{"id": [1], "cmd": "#, None),
+        ("not json", None),
+    ] {
+        let response = client.request(line, &mut event_loop, &mut state);
+        assert_eq!(response["ok"].as_bool(), Some(false), "{} was accepted", line);
+        assert_eq!(response.get("id").cloned(), id, "{}", line);
+    }
+
+    // The connection stays usable after errors
+    let response = client.request(r#"{"cmd": "state"}"#, &mut event_loop, &mut state);
+    assert_eq!(response["state"]["expression"].as_str(), Some("neutral"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn subscribers_get_events() {
+    let dir = scratch_dir("subscribe");
+    let socket = dir.join("control.sock");
+    let mut state = test_face(&dir);
+    let mut event_loop: EventLoop<'static, TestFace> = EventLoop::try_new().unwrap();
+    let _server = ControlServer::bind(&socket, &event_loop.handle()).unwrap();
+
+    let mut watcher = Client::connect(&socket);
+    let response = watcher.request(r#"{"cmd": "subscribe"}"#, &mut event_loop, &mut state);
+    assert_eq!(response["ok"].as_bool(), Some(true));
+
+    let mut client = Client::connect(&socket);
+    client.request(r#"{"cmd": "set", "expression": "sad", "duration": 0}"#, &mut event_loop, &mut state);
+
+    let event = watcher.receive(&mut event_loop, &mut state);
+    assert_eq!(event["event"].as_str(), Some("transition_started"));
+    assert_eq!(event["expression"].as_str(), Some("sad"));
+
+    state.face.poll(Instant::now() + Duration::from_millis(1));
+    let event = watcher.receive(&mut event_loop, &mut state);
+    assert_eq!(event["event"].as_str(), Some("transition_finished"));
+
+    // More than the socket holds while the watcher isn't reading, none of it is lost or cut short
+    for region in 0..20_000 {
+        state.face.publish(Event::Tapped { region });
+    }
+    let mut region = 0;
+    while region < 20_000 {
+        let event = watcher.receive(&mut event_loop, &mut state);
+        if event["event"].as_str() == Some("tapped") {
+            assert_eq!(event["region"].as_u64(), Some(region));
+            region += 1;
+        }
+    }
+
+    // A watcher that never catches up is disconnected rather than skipping events
+    for region in 0..200_000 {
+        state.face.publish(Event::Tapped { region });
+    }
+    watcher.receive_all(&mut event_loop, &mut state);
+    let response = client.request(r#"{"cmd": "state"}"#, &mut event_loop, &mut state);
+    assert_eq!(response["ok"].as_bool(), Some(true));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn live_sockets_are_not_taken_over() {
+    let dir = scratch_dir("live");
+    let socket = dir.join("control.sock");
+    let event_loop: EventLoop<'static, TestFace> = EventLoop::try_new().unwrap();
+
+    let server = ControlServer::bind(&socket, &event_loop.handle()).unwrap();
+    assert_eq!(fs::metadata(&socket).unwrap().permissions().mode() & 0o777, 0o600);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    let err = ControlServer::bind(&socket, &event_loop.handle()).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::AddrInUse);
+
+    // Dropping the server cleans up after it
+    drop(server);
+    assert!(!socket.exists());
+
+    // A stale file left by a crash gets replaced
+    fs::write(&socket, "").unwrap();
+    let _server = ControlServer::bind(&socket, &event_loop.handle()).unwrap();
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn expressions_with_other_shapes_are_rejected() {
+    let dir = scratch_dir("shapes");
+    let mut state = test_face(&dir);
+    let wink = r#"[[[0, 0], [10, 0], [10, 10]], [[20, 0], [30, 0], [30, 10]]]"#;
+    fs::write(dir.join("face.hvf"), format!(r#"{{"expression": {{"neutral": {}, "wink": {}}}}}"#, common::square(10.0), wink)).unwrap();
+    state.face.hvf = Some(HVFLoader::new(dir.join("face.hvf").to_str().unwrap()).unwrap());
+
+    // Two shapes don't morph from the single square, and nothing of the command is carried out
+    for line in [
+        r#"{"cmd": "set", "expression": "wink"}"#,
+        r#"{"cmd": "sequence", "steps": [{"expression": "neutral"}, {"expression": "wink"}]}"#,
+        r#"{"cmd": "blend", "weights": {"neutral": 1.0, "wink": 1.0}}"#,
+    ] {
+        let err = state.handle_command(Request::parse(line).unwrap().command).unwrap_err();
+        assert!(err.contains("wink"), "{}", err);
+    }
+    assert_eq!(state.face.animator.queued(), 0);
+    assert_eq!(state.face.expression, "neutral");
+
+    let neutral = state.face.ring("neutral").unwrap();
+    let wink = state.face.ring("wink").unwrap();
+    assert!(Animator::blend(&[(&neutral, 1.0), (&wink, 1.0)]).is_none());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
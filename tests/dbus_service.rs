@@ -0,0 +1,214 @@
+mod common;
+
+use std::{
+    collections::HashMap, fs, io::{BufRead, BufReader}, path::Path, process::{Child, Command, Stdio},
+    sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc}, thread, time::{Duration, Instant}
+};
+
+use calloop::EventLoop;
+use common::{scratch_dir, test_face, TestFace};
+use hyogen_ui::{control::dbus::{DbusService, BUS_NAME, OBJECT_PATH}, events::Event};
+use zbus::{blocking, CacheProperties};
+
+const INTERFACE: &str = "org.osmos.Hyogen";
+
+// A dbus-daemon of our own, so the tests don't need (or disturb) a session bus.
+struct Bus {
+    daemon: Child,
+    address: String,
+}
+
+impl Bus {
+    // None if dbus-daemon isn't installed.
+    fn start(dir: &Path) -> Option<Self> {
+        let config = dir.join("bus.conf");
+        fs::write(&config, format!(r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-BUS Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:path={}</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#, dir.join("bus.sock").display())).unwrap();
+
+        let mut daemon = match Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", config.display()))
+            .args(["--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .spawn()
+        {
+            Ok(daemon) => daemon,
+            Err(err) => {
+                eprintln!("Skipping, dbus-daemon is not available: {}", err);
+                return None;
+            }
+        };
+
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut address).unwrap();
+        Some(Self { daemon, address: address.trim().to_string() })
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+// Runs the face and its D-Bus service on a thread of its own, like hyogen-ui's event loop.
+struct Face {
+    stop: Arc<AtomicBool>,
+    taps: mpsc::Sender<usize>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Face {
+    fn start(dir: &Path, address: &str) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (taps, tapped) = mpsc::channel();
+        let (ready, started) = mpsc::channel();
+
+        let (dir, address, running) = (dir.to_path_buf(), address.to_string(), stop.clone());
+        let thread = thread::spawn(move || {
+            let mut state: TestFace = test_face(&dir);
+            let mut event_loop: EventLoop<'static, TestFace> = EventLoop::try_new().unwrap();
+            let _service = DbusService::start(Some(&address), &event_loop.handle()).unwrap();
+            ready.send(()).unwrap();
+
+            while !running.load(Ordering::Relaxed) {
+                event_loop.dispatch(Some(Duration::from_millis(10)), &mut state).unwrap();
+                while let Ok(region) = tapped.try_recv() {
+                    state.face.publish(Event::Tapped { region });
+                }
+                state.face.poll(Instant::now());
+            }
+        });
+
+        started.recv_timeout(Duration::from_secs(5)).unwrap();
+        Self { stop, taps, thread: Some(thread) }
+    }
+}
+
+impl Drop for Face {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn proxy(connection: &blocking::Connection) -> blocking::Proxy<'static> {
+    blocking::proxy::Builder::new(connection)
+        .destination(BUS_NAME).unwrap()
+        .path(OBJECT_PATH).unwrap()
+        .interface(INTERFACE).unwrap()
+        .cache_properties(CacheProperties::No)
+        .build()
+        .unwrap()
+}
+
+// Wait for the next `name` signal, collected on a thread so a missing signal fails instead of hanging.
+fn expect_signal(proxy: &blocking::Proxy<'static>, name: &'static str) -> mpsc::Receiver<zbus::Message> {
+    let mut signals = proxy.receive_signal(name).unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        if let Some(message) = signals.next() {
+            let _ = sender.send(message);
+        }
+    });
+    receiver
+}
+
+#[test]
+fn methods_properties_and_signals() {
+    let dir = scratch_dir("dbus");
+    let Some(bus) = Bus::start(&dir) else {
+        return;
+    };
+    let _face = Face::start(&dir, &bus.address);
+
+    let connection = blocking::connection::Builder::address(bus.address.as_str()).unwrap().build().unwrap();
+    let proxy = proxy(&connection);
+
+    let expressions: Vec<String> = proxy.call("ListExpressions", &()).unwrap();
+    assert_eq!(expressions, ["happy", "neutral", "sad"]);
+
+    // Setting an expression announces the transition, and its end
+    let started = expect_signal(&proxy, "TransitionStarted");
+    let finished = expect_signal(&proxy, "TransitionFinished");
+    let () = proxy.call("SetExpression", &("happy", 0.2, "out-cubic")).unwrap();
+    assert_eq!(proxy.get_property::<String>("Expression").unwrap(), "happy");
+    assert!(proxy.get_property::<bool>("Animating").unwrap());
+
+    let message = started.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(message.body().deserialize::<String>().unwrap(), "happy");
+    let message = finished.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(message.body().deserialize::<String>().unwrap(), "happy");
+    assert!(!proxy.get_property::<bool>("Animating").unwrap());
+
+    // Sequences and blends
+    let steps = vec![("sad".to_string(), 0.1, String::new()), ("neutral".to_string(), 0.1, "in-out-sine".to_string())];
+    let () = proxy.call("PlaySequence", &(steps, true)).unwrap();
+    assert_eq!(proxy.get_property::<String>("Expression").unwrap(), "neutral");
+
+    let weights = HashMap::from([("happy".to_string(), 0.5), ("sad".to_string(), 0.5)]);
+    let () = proxy.call("Blend", &(weights, 0.0, "")).unwrap();
+    assert_eq!(proxy.get_property::<String>("Expression").unwrap(), "blend");
+
+    let () = proxy.call("Pause", &()).unwrap();
+    assert!(proxy.get_property::<bool>("Paused").unwrap());
+    let () = proxy.call("Resume", &()).unwrap();
+    assert!(!proxy.get_property::<bool>("Paused").unwrap());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn invalid_calls_are_errors() {
+    let dir = scratch_dir("dbus-invalid");
+    let Some(bus) = Bus::start(&dir) else {
+        return;
+    };
+    let _face = Face::start(&dir, &bus.address);
+
+    let connection = blocking::connection::Builder::address(bus.address.as_str()).unwrap().build().unwrap();
+    let proxy = proxy(&connection);
+
+    let err = proxy.call::<_, _, ()>("SetExpression", &("angry", 0.3, "")).unwrap_err();
+    assert!(err.to_string().contains("Unknown expression angry"), "{}", err);
+    assert!(proxy.call::<_, _, ()>("SetExpression", &("happy", 0.3, "bouncy")).is_err());
+    assert!(proxy.call::<_, _, ()>("SetExpression", &("happy", -1.0, "")).is_err());
+
+    // Nothing changed
+    assert_eq!(proxy.get_property::<String>("Expression").unwrap(), "neutral");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn taps_are_signalled() {
+    let dir = scratch_dir("dbus-taps");
+    let Some(bus) = Bus::start(&dir) else {
+        return;
+    };
+    let face = Face::start(&dir, &bus.address);
+
+    let connection = blocking::connection::Builder::address(bus.address.as_str()).unwrap().build().unwrap();
+    let proxy = proxy(&connection);
+
+    let tapped = expect_signal(&proxy, "RegionTapped");
+    face.taps.send(2).unwrap();
+    let message = tapped.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(message.body().deserialize::<u32>().unwrap(), 2);
+
+    drop(face);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use std::{cell::RefCell, fs, rc::Rc, time::{Duration, Instant}};
+
+use common::{scratch_dir, test_face};
+use hyogen_ui::{
+    control::protocol::{Command, Request},
+    events::{Event, EventFilter, EventKind},
+};
+
+// Collects what a subscriber gets.
+fn record(filter: EventFilter, face: &mut hyogen_ui::face::FaceState) -> Rc<RefCell<Vec<Event>>> {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let recorded = events.clone();
+    face.events.subscribe(filter, move |event| {
+        recorded.borrow_mut().push(event.clone());
+        true
+    });
+    events
+}
+
+#[test]
+fn animation_events_are_published() {
+    let dir = scratch_dir("events");
+    let mut state = test_face(&dir);
+    let face = &mut state.face;
+    let now = Instant::now();
+    face.poll(now);
+    let events = record(EventFilter::All, face);
+
+    let set = Request::parse(r#"{"cmd": "set", "expression": "happy", "duration": 0.1}"#).unwrap();
+    face.apply(set.command, now).unwrap();
+    face.apply(Command::Pause, now).unwrap();
+    face.apply(Command::Pause, now).unwrap();
+    face.apply(Command::Resume, now).unwrap();
+
+    // Nothing finishes early
+    face.poll(now + Duration::from_millis(50));
+    face.poll(now + Duration::from_millis(100));
+    face.poll(now + Duration::from_millis(200));
+
+    assert_eq!(*events.borrow(), [
+        Event::TransitionStarted { expression: "happy".to_string() },
+        Event::Paused,
+        Event::Resumed,
+        Event::TransitionFinished { expression: "happy".to_string() },
+        Event::Settled,
+    ]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn filters_pick_events() {
+    let dir = scratch_dir("events-filter");
+    let mut state = test_face(&dir);
+    let face = &mut state.face;
+
+    let taps = record(EventFilter::Kinds(vec![EventKind::Tapped]), face);
+    let lifecycle = record(EventFilter::parse(&["lifecycle", "paused"]).unwrap(), face);
+
+    face.publish(Event::Tapped { region: 1 });
+    face.publish(Event::Connected);
+    face.publish(Event::PointerEntered { surface: 0 });
+    face.publish(Event::Paused);
+
+    assert_eq!(*taps.borrow(), [Event::Tapped { region: 1 }]);
+    assert_eq!(*lifecycle.borrow(), [Event::Connected, Event::Paused]);
+    assert!(EventFilter::parse(&["tapped", "typo"]).is_err());
+
+    // Subscribers returning false are dropped
+    face.events.subscribe(EventFilter::All, |_| false);
+    assert_eq!(face.events.len(), 3);
+    face.publish(Event::Reloaded { restart: Vec::new() });
+    assert_eq!(face.events.len(), 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use std::{fs::{self, File}, path::Path};
+
+use common::{scratch_dir, square};
+use hyogen_ui::export::{self, ExportOptions};
+
+// Three frames at 10 fps, half of 80x48 unless `extra` says otherwise.
+fn export_options(dir: &Path, out: &str, extra: &[&str]) -> ExportOptions {
+    let hvf = dir.join("face.hvf");
+    fs::write(&hvf, format!(r#"{{"expression": {{"neutral": {}, "happy": {}}}}}"#, square(100.0), square(400.0))).unwrap();
+
+    let out = dir.join(out);
+    let mut args = vec![
+        "--hvf", hvf.to_str().unwrap(), "--sequence", "neutral,happy:0.2", "--fps", "10", "--size", "80x48",
+        "--scale", "0.5", "--out", out.to_str().unwrap(),
+    ];
+    args.extend_from_slice(extra);
+    ExportOptions::from_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>()).unwrap()
+}
+
+#[test]
+fn gif_frames_decode() {
+    let dir = scratch_dir("export-gif");
+    let options = export_options(&dir, "face.gif", &["--loop", "3"]);
+    assert_eq!(export::export(&options).unwrap(), 3);
+
+    let mut decoder = gif::DecodeOptions::new().read_info(File::open(&options.render.out).unwrap()).unwrap();
+    assert_eq!((decoder.width(), decoder.height()), (40, 24));
+
+    let mut frames = 0;
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        // Hundredths of a second
+        assert_eq!(frame.delay, 10);
+        assert_eq!((frame.width, frame.height), (40, 24));
+        frames += 1;
+    }
+    assert_eq!(frames, 3);
+    assert_eq!(decoder.repeat(), gif::Repeat::Finite(3));
+
+    // No --loop loops forever
+    let forever = export_options(&dir, "forever.gif", &[]);
+    export::export(&forever).unwrap();
+    let mut decoder = gif::DecodeOptions::new().read_info(File::open(&forever.render.out).unwrap()).unwrap();
+    while decoder.read_next_frame().unwrap().is_some() {}
+    assert_eq!(decoder.repeat(), gif::Repeat::Infinite);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn apng_frames_decode() {
+    let dir = scratch_dir("export-apng");
+    let options = export_options(&dir, "face.png", &["--loop", "2"]);
+    assert_eq!(export::export(&options).unwrap(), 3);
+
+    let mut reader = png::Decoder::new(File::open(&options.render.out).unwrap()).read_info().unwrap();
+    let info = reader.info();
+    assert_eq!((info.width, info.height), (40, 24));
+    let control = info.animation_control.unwrap();
+    assert_eq!((control.num_frames, control.num_plays), (3, 2));
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    for _ in 0..control.num_frames {
+        let output = reader.next_frame(&mut buffer).unwrap();
+        assert_eq!((output.width, output.height), (40, 24));
+
+        let frame = reader.info().frame_control.unwrap();
+        assert_eq!((frame.delay_num, frame.delay_den), (100, 1000));
+    }
+    assert!(reader.next_frame(&mut buffer).is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
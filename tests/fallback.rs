@@ -0,0 +1,77 @@
+mod common;
+
+use std::{fs, time::{Duration, Instant}};
+
+use common::{scratch_dir, square};
+use hyogen_ui::{
+    cli::{self, Cli},
+    face::FaceState,
+    geometry::Point,
+    hvf::loader::{HVFLoader, BUILTIN_CLASS},
+    include_hvf,
+    placement::Placement,
+};
+
+#[test]
+fn builtin_face_is_embedded() {
+    let builtin = HVFLoader::builtin();
+    assert_eq!(builtin.ids(BUILTIN_CLASS), ["blink", "error", "neutral"]);
+
+    // Downstream binaries embed their own packs the same way
+    let pack = include_hvf!("../assets/default.hvf");
+    assert_eq!(pack.get(BUILTIN_CLASS, "error").unwrap().values().len(), builtin.get(BUILTIN_CLASS, "error").unwrap().values().len());
+
+    // Broken packs are errors rather than panics
+    for broken in ["", "[]", r#"{"expression": []}"#, r#"{"expression": {"neutral": [[1, 2]]}}"#, r#"{"expression": {"neutral": [[[0, 0]]]}}"#] {
+        assert!(HVFLoader::parse(broken).is_err(), "{}", broken);
+    }
+}
+
+#[test]
+fn builtin_face_recovers_once_a_file_loads() {
+    let dir = scratch_dir("fallback");
+    let config = dir.join("config.toml");
+    fs::write(&config, "[face]\nhvf = \"face.hvf\"\nexpression = \"happy\"\n").unwrap();
+    let Cli::Run(run) = cli::parse(&["--config".to_string(), config.display().to_string()]).unwrap() else {
+        panic!("Expected the run command");
+    };
+
+    // As main starts it when face.hvf doesn't load
+    let builtin = HVFLoader::builtin();
+    let mut face = FaceState::new(builtin.get(BUILTIN_CLASS, "error").unwrap(), Placement::default());
+    face.expression = "error".to_string();
+    face.hvf = Some(builtin);
+    face.fallback = true;
+    face.configure(run.resolve().unwrap());
+    face.settings = Some(run);
+
+    // Still missing, then without the configured expression
+    let now = Instant::now();
+    face.reload(now);
+    assert!(face.fallback);
+    fs::write(dir.join("face.hvf"), format!(r#"{{"expression": {{"sad": {}}}}}"#, square(30.0))).unwrap();
+    face.reload(now);
+    assert!(face.fallback);
+    assert_eq!(face.expression, "error");
+    assert_eq!(face.class, BUILTIN_CLASS);
+
+    // A single square doesn't morph from the built-in eyes and mouth, it just shows up
+    fs::write(dir.join("face.hvf"), format!(r#"{{"expression": {{"neutral": {}, "happy": {}}}}}"#, square(10.0), square(20.0))).unwrap();
+    face.reload(now);
+    assert!(!face.fallback);
+    assert_eq!(face.expression, "happy");
+    assert_eq!(face.hvf_path.as_deref(), Some(dir.join("face.hvf").to_str().unwrap()));
+    let path = face.animator.get_path(now + Duration::from_secs(1));
+    assert_eq!(path.len(), 1);
+    assert_eq!(path[0], [Point::new(0.0, 0.0), Point::new(20.0, 0.0), Point::new(20.0, 20.0), Point::new(0.0, 20.0)]);
+    assert!(face.farewell.is_none());
+
+    // A goodbye that can't morph from the face is passed over for the blink
+    let goodbye = "[[[0.0, 0.0], [5.0, 0.0], [5.0, 5.0]], [[0.0, 0.0], [5.0, 5.0], [0.0, 5.0]]]";
+    fs::write(dir.join("face.hvf"), format!(r#"{{"expression": {{"happy": {}, "goodbye": {}, "blink": {}}}}}"#, square(20.0), goodbye, square(1.0))).unwrap();
+    face.reload(now);
+    assert_eq!(face.farewell.as_ref().map(|ring| ring.values().len()), Some(1));
+    assert_eq!(face.farewell.as_ref().unwrap().values()[0][1], Point::new(1.0, 0.0));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use std::{fs::{self, File}, path::Path};
+
+use common::{scratch_dir, square};
+use hyogen_ui::{
+    headless::{self, HeadlessError, RenderOptions, MAX_SIZE},
+    renderer::{Color, Outline, Style},
+};
+
+const RED: [u8; 4] = [0xFF, 0, 0, 0xFF];
+const BLACK: [u8; 4] = [0, 0, 0, 0xFF];
+
+// Neutral and happy squares of 100 and 400 design units, and a face with two of them.
+fn write_face(dir: &Path) -> String {
+    let path = dir.join("face.hvf");
+    let two = "[[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0]], [[20.0, 20.0], [30.0, 20.0], [30.0, 30.0]]]";
+    let hvf = format!(r#"{{"expression": {{"neutral": {}, "happy": {}, "two": {}}}}}"#, square(100.0), square(400.0), two);
+    fs::write(&path, hvf).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+// 80x48 is a tenth of the design size, so design units map to pixels by dividing by 10.
+fn options(dir: &Path, sequence: &str) -> RenderOptions {
+    let (hvf, out) = (write_face(dir), dir.join("frames"));
+    let args = ["--hvf", &hvf, "--sequence", sequence, "--fps", "10", "--size", "80x48", "--out", out.to_str().unwrap()];
+    let mut options = RenderOptions::from_args(&args.map(String::from)).unwrap();
+    options.style = Style { outline: Outline::Stroke, fill: Some(Color::rgba(0xFF, 0, 0, 0xFF)), stroke: Color::rgba(0xFF, 0, 0, 0xFF), stroke_width: 0.0 };
+    options
+}
+
+fn decode(path: &Path) -> (u32, u32, Vec<u8>) {
+    let mut reader = png::Decoder::new(File::open(path).unwrap()).read_info().unwrap();
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).unwrap();
+    data.truncate(info.buffer_size());
+    (info.width, info.height, data)
+}
+
+fn pixel(image: &(u32, u32, Vec<u8>), x: u32, y: u32) -> [u8; 4] {
+    let offset = ((y * image.0 + x) * 4) as usize;
+    image.2[offset..offset + 4].try_into().unwrap()
+}
+
+#[test]
+fn sequences_become_numbered_frames() {
+    let dir = scratch_dir("headless-frames");
+    let options = options(&dir, "neutral,happy:0.1,neutral:0.1");
+
+    // One frame per tick plus the last one
+    assert_eq!(headless::render_to_files(&options).unwrap(), 3);
+
+    let mut names: Vec<String> = fs::read_dir(&options.out).unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["frame_00000.png", "frame_00001.png", "frame_00002.png"]);
+
+    // Only the happy square reaches (30, 30)
+    let first = decode(&options.out.join("frame_00000.png"));
+    let happy = decode(&options.out.join("frame_00001.png"));
+    assert_eq!((happy.0, happy.1), (80, 48));
+    assert_eq!(pixel(&first, 5, 5), RED);
+    assert_eq!(pixel(&first, 30, 30), BLACK);
+    assert_eq!(pixel(&happy, 30, 30), RED);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn expressions_with_other_shapes_are_rejected() {
+    let dir = scratch_dir("headless-shapes");
+    let options = options(&dir, "neutral,happy:0.1,two:0.1");
+
+    match headless::render_to_files(&options) {
+        Err(HeadlessError::InvalidArgument(message)) => {
+            assert!(message.contains("happy") && message.contains("two"), "{}", message);
+        }
+        other => panic!("Expected an error, got {:?}", other),
+    }
+    // Checked before rendering, nothing was written
+    assert_eq!(fs::read_dir(&options.out).unwrap().count(), 0);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sizes_are_capped() {
+    assert_eq!(headless::parse_size("80x48").unwrap(), (80, 48));
+    assert_eq!(headless::parse_size(&format!("{}x{}", MAX_SIZE, MAX_SIZE)).unwrap(), (MAX_SIZE, MAX_SIZE));
+    for size in ["0x48", "80x", &format!("{}x48", MAX_SIZE + 1), "80x4294967295"] {
+        assert!(headless::parse_size(size).is_err(), "{}", size);
+    }
+}
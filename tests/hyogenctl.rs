@@ -0,0 +1,99 @@
+mod common;
+
+use std::{
+    fs, io::{BufRead, BufReader, Write}, os::unix::net::UnixListener, path::Path, process::{Command, Output}, thread
+};
+
+use common::scratch_dir;
+use hyogen_ui::control::client::{ClientError, ControlClient};
+use serde_json::Value;
+
+fn hyogenctl(socket: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_hyogenctl")).arg("--socket").arg(socket).args(args).output().unwrap()
+}
+
+// Run hyogenctl against a face that answers `response` to whatever it's sent, returns the request and the output.
+fn exchange(socket: &Path, args: &[&str], response: &str) -> (Value, Output) {
+    let listener = UnixListener::bind(socket).unwrap();
+    let response = response.to_string();
+    let face = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line).unwrap();
+        writeln!(&stream, "{}", response).unwrap();
+        serde_json::from_str::<Value>(&line).unwrap()
+    });
+
+    let output = hyogenctl(socket, args);
+    let request = face.join().unwrap();
+    fs::remove_file(socket).unwrap();
+    (request, output)
+}
+
+#[test]
+fn missing_faces_are_reported() {
+    let dir = scratch_dir("ctl-missing");
+    let socket = dir.join("control.sock");
+
+    assert!(matches!(ControlClient::connect(&socket), Err(ClientError::NotRunning(path)) if path == socket));
+
+    // Left behind by a face that crashed
+    drop(UnixListener::bind(&socket).unwrap());
+    assert!(matches!(ControlClient::connect(&socket), Err(ClientError::NotRunning(_))));
+
+    let output = hyogenctl(&socket, &["state"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not running"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn commands_become_requests() {
+    let dir = scratch_dir("ctl-requests");
+    let socket = dir.join("control.sock");
+
+    let (request, output) = exchange(&socket, &["set", "happy", "--duration", "1s", "--ease", "out-cubic"], r#"{"ok": true}"#);
+    assert!(output.status.success());
+    assert_eq!(request, serde_json::from_str::<Value>(r#"{"cmd": "set", "expression": "happy", "duration": "1s", "easing": "out-cubic"}"#).unwrap());
+
+    let state = r#"{"ok": true, "state": {"expression": "sad", "animating": true, "paused": false, "queued": 2}}"#;
+    let (request, output) = exchange(&socket, &["state", "--json"], state);
+    assert_eq!(request["cmd"].as_str(), Some("state"));
+    let printed: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(printed["expression"].as_str(), Some("sad"));
+    assert_eq!(printed["queued"].as_u64(), Some(2));
+
+    let (_, output) = exchange(&socket, &["state"], state);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "sad (animating), 2 queued\n");
+
+    // Refused commands fail with the face's reason
+    let (_, output) = exchange(&socket, &["set", "angry"], r#"{"ok": false, "error": "Unknown expression angry"}"#);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown expression angry"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn bad_arguments_are_usage_errors() {
+    let dir = scratch_dir("ctl-usage");
+    let socket = dir.join("control.sock");
+
+    for (args, message) in [
+        (&["set"][..], "set expects an expression"),
+        (&["set", "happy", "--speed", "2"], "Unknown option --speed"),
+        (&["set", "happy", "--duration"], "--duration expects a value"),
+        (&["state", "--yaml"], "Unknown option --yaml"),
+        (&["blink", "twice"], "blink takes no arguments"),
+        (&["dance"], "Unknown command dance"),
+    ] {
+        // Nothing is listening, these never get as far as connecting
+        let output = hyogenctl(&socket, args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(message) && stderr.contains("Usage"), "{:?}: {}", args, stderr);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
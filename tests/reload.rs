@@ -0,0 +1,105 @@
+mod common;
+
+use std::{cell::Cell, fs, rc::Rc, time::{Duration, Instant}};
+
+use calloop::EventLoop;
+use common::{scratch_dir, square, test_face};
+use hyogen_ui::{
+    cli::{self, Cli},
+    control::protocol::Request,
+    geometry::Point,
+    renderer::Color,
+    watch,
+};
+use smithay_client_toolkit::shell::wlr_layer::Layer;
+
+#[test]
+fn reload_applies_what_it_can() {
+    let dir = scratch_dir("reload");
+    let mut state = test_face(&dir);
+    let face = &mut state.face;
+
+    let config = dir.join("config.toml");
+    fs::write(&config, "[face]\nhvf = \"face.hvf\"\n").unwrap();
+    let Cli::Run(run) = cli::parse(&["--config".to_string(), config.display().to_string()]).unwrap() else {
+        panic!("Expected the run command");
+    };
+    face.configure(run.resolve().unwrap());
+    face.settings = Some(run);
+    let set = Request::parse(r#"{"cmd": "set", "expression": "happy", "duration": 0}"#).unwrap();
+    face.apply(set.command, Instant::now()).unwrap();
+
+    // A second pack, with a blink, and settings that can change while running and some that can't
+    let pack = format!(r#"{{"expression": {{"neutral": {}, "happy": {}, "blink": {}}}}}"#, square(11.0), square(21.0), square(1.0));
+    fs::write(dir.join("pack.hvf"), pack).unwrap();
+    fs::write(&config, r#"
+[face]
+hvf = ["missing.hvf", "pack.hvf"]
+
+[theme]
+background = "202020FF"
+
+[blink]
+interval = "1s"
+
+[render]
+fps-cap = 20
+
+[placement]
+layer = "overlay"
+
+[ipc]
+dbus = false
+"#).unwrap();
+
+    let now = Instant::now();
+    let restart = face.reload(now);
+    assert_eq!(restart, ["ipc.dbus"]);
+    assert_eq!(face.hvf_path.as_deref(), Some(dir.join("pack.hvf").to_str().unwrap()));
+    assert_eq!(face.background, Color::from_hex("202020FF").unwrap());
+    assert!(face.blink.is_some());
+    assert_eq!(face.config.fps_cap, Some(20.0));
+    assert_eq!(face.config.placement.layer, Layer::Overlay);
+
+    // The face moves over to the same expression in the new pack
+    assert_eq!(face.expression, "happy");
+    assert!(!face.animator.is_settled(now));
+    let path = face.animator.get_path(now + Duration::from_secs(1));
+    assert!(path[0].contains(&Point::new(21.0, 21.0)), "{:?}", path);
+
+    // Broken settings are ignored as a whole
+    fs::write(&config, "[theme]\nbackground = \"nope\"\n").unwrap();
+    assert!(face.reload(now).is_empty());
+    assert_eq!(face.background, Color::from_hex("202020FF").unwrap());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn watched_files_call_back_when_replaced() {
+    let dir = scratch_dir("watch");
+    let path = dir.join("config.toml");
+    fs::write(&path, "").unwrap();
+
+    let mut event_loop: EventLoop<'static, ()> = EventLoop::try_new().unwrap();
+    let changes = Rc::new(Cell::new(0));
+    let counted = changes.clone();
+    watch::watch_file(&path, &event_loop.handle(), move |_| counted.set(counted.get() + 1)).unwrap();
+
+    // Other files in the directory don't count
+    fs::write(dir.join("other.toml"), "").unwrap();
+    event_loop.dispatch(Some(Duration::from_millis(50)), &mut ()).unwrap();
+    assert_eq!(changes.get(), 0);
+
+    // Like an editor saving to a temporary file first
+    fs::write(dir.join("config.toml.tmp"), "[render]\nfps-cap = 30\n").unwrap();
+    fs::rename(dir.join("config.toml.tmp"), &path).unwrap();
+    event_loop.dispatch(Some(Duration::from_secs(1)), &mut ()).unwrap();
+    assert_eq!(changes.get(), 1);
+
+    fs::write(&path, "").unwrap();
+    event_loop.dispatch(Some(Duration::from_secs(1)), &mut ()).unwrap();
+    assert_eq!(changes.get(), 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,182 @@
+use hyogen_ui::{
+    geometry::{Point, Shape},
+    layout::{hit_test, Transform},
+    renderer::{
+        damage::{frame_bounds, Damage, DamageTracker, Rect},
+        memory::MemoryBackend,
+        raster::SoftwareRenderer,
+        Color, Frame, Outline, PixelBuffer, Renderer, Style,
+    },
+};
+
+const RED: Color = Color::rgba(0xFF, 0, 0, 0xFF);
+const BLUE: Color = Color::rgba(0, 0, 0xFF, 0xFF);
+
+fn rect(x: f64, y: f64, width: f64, height: f64) -> Vec<Point> {
+    vec![Point::new(x, y), Point::new(x + width, y), Point::new(x + width, y + height), Point::new(x, y + height)]
+}
+
+fn shape(paths: &[Vec<Point>]) -> Shape {
+    paths.iter().collect()
+}
+
+// Red with the thinnest blue outline.
+fn filled(paths: Shape) -> Frame {
+    Frame::new(paths, vec![Style { outline: Outline::Stroke, fill: Some(RED), stroke: BLUE, stroke_width: 0.0 }], Color::BLACK)
+}
+
+fn render(frame: &Frame, width: u32, height: u32) -> MemoryBackend {
+    let mut backend = MemoryBackend::new(width, height);
+    backend.draw(&mut SoftwareRenderer::new(), frame);
+    backend
+}
+
+#[test]
+fn paths_are_filled() {
+    let backend = render(&filled(shape(&[rect(10.0, 10.0, 20.0, 10.0)])), 40, 40);
+
+    // Pixels whose centers are inside the rectangle, the outline on the far edges, nothing else
+    for (x, y, color) in [
+        (11, 11, RED), (29, 19, RED), (20, 15, RED),
+        (30, 15, BLUE), (20, 20, BLUE),
+        (9, 15, Color::BLACK), (31, 15, Color::BLACK), (20, 9, Color::BLACK), (20, 21, Color::BLACK),
+    ] {
+        assert_eq!(backend.pixel(x, y), color, "{}, {}", x, y);
+    }
+}
+
+#[test]
+fn overlapping_parts_of_a_path_are_holes() {
+    // A square around a square, joined by an edge drawn there and back
+    let mut ring = rect(0.0, 0.0, 40.0, 40.0);
+    ring.extend([Point::new(0.0, 0.0), Point::new(10.0, 10.0), Point::new(10.0, 30.0), Point::new(30.0, 30.0), Point::new(30.0, 10.0), Point::new(10.0, 10.0)]);
+    let paths = shape(&[ring]);
+    let backend = render(&filled(paths.clone()), 40, 40);
+
+    assert_eq!(backend.pixel(5, 20), RED);
+    assert_eq!(backend.pixel(35, 20), RED);
+    assert_eq!(backend.pixel(20, 5), RED);
+    assert_eq!(backend.pixel(20, 20), Color::BLACK);
+
+    // Hit-testing follows the same rule
+    assert_eq!(hit_test(&paths, 5.0, 20.0), Some(0));
+    assert_eq!(hit_test(&paths, 20.0, 20.0), None);
+    assert_eq!(hit_test(&paths, 50.0, 20.0), None);
+}
+
+#[test]
+fn paths_are_stroked_at_the_transformed_width() {
+    let style = Style { outline: Outline::Stroke, stroke: Color::WHITE, stroke_width: 2.0, fill: None };
+    let frame = Frame::new(shape(&[rect(10.0, 10.0, 20.0, 20.0)]), vec![style], Color::TRANSPARENT)
+        .with_transform(Transform { scale_x: 2.0, scale_y: 2.0, offset_x: 0.0, offset_y: 0.0 });
+    let backend = render(&frame, 80, 80);
+
+    // The left edge at x = 20 is 4 pixels wide once scaled
+    assert_eq!(backend.pixel(20, 40), Color::WHITE);
+    assert_eq!(backend.pixel(18, 40), Color::WHITE);
+    assert_eq!(backend.pixel(21, 40), Color::WHITE);
+    assert_eq!(backend.pixel(16, 40), Color::TRANSPARENT);
+    assert_eq!(backend.pixel(24, 40), Color::TRANSPARENT);
+    // Unfilled inside
+    assert_eq!(backend.pixel(40, 40), Color::TRANSPARENT);
+    assert_eq!(backend.pixel(59, 59), Color::WHITE);
+}
+
+#[test]
+fn points_are_plotted_by_default() {
+    let frame = Frame::new(shape(&[rect(10.0, 10.0, 20.0, 20.0)]), vec![Style::default()], Color::BLACK)
+        .with_transform(Transform { scale_x: 2.0, scale_y: 2.0, offset_x: 0.0, offset_y: 0.0 });
+    let backend = render(&frame, 80, 80);
+
+    // A single pixel per corner, nothing between them
+    let lit: Vec<(u32, u32)> = (0..80).flat_map(|y| (0..80).map(move |x| (x, y)))
+        .filter(|&(x, y)| backend.pixel(x, y) == Color::WHITE)
+        .collect();
+    assert_eq!(lit, [(20, 20), (60, 20), (20, 60), (60, 60)]);
+}
+
+#[test]
+fn premultiplied_buffers_scale_colors_by_alpha() {
+    let half_red = Color::rgba(0xFF, 0, 0, 0x80);
+    let mut data = vec![0; 8];
+    let mut target = PixelBuffer::new(&mut data, 2, 1, 8).with_premultiplied_alpha();
+    target.clear(half_red);
+    target.put_pixel(1, 0, Color::rgba(0x40, 0x80, 0xFF, 0xFF));
+
+    // Reading gives back the plain colors
+    assert_eq!(target.pixel(0, 0), half_red);
+    assert_eq!(target.pixel(1, 0), Color::rgba(0x40, 0x80, 0xFF, 0xFF));
+    // B, G, R, A with the channels scaled, opaque pixels are untouched
+    assert_eq!(data, [0, 0, 0x80, 0x80, 0xFF, 0x80, 0x40, 0xFF]);
+}
+
+#[test]
+fn later_paths_take_later_styles() {
+    let styles = vec![
+        Style { outline: Outline::Stroke, fill: Some(RED), stroke: RED, stroke_width: 0.0 },
+        Style { outline: Outline::Stroke, fill: Some(Color::WHITE), stroke: Color::WHITE, stroke_width: 0.0 },
+    ];
+    let frame = Frame::new(shape(&[rect(0.0, 0.0, 20.0, 20.0), rect(10.0, 0.0, 20.0, 20.0), rect(0.0, 25.0, 5.0, 5.0)]), styles, Color::BLACK);
+    let backend = render(&frame, 40, 40);
+
+    assert_eq!(backend.pixel(5, 5), RED);
+    // Drawn over the first one
+    assert_eq!(backend.pixel(15, 5), Color::WHITE);
+    // The last style is reused
+    assert_eq!(backend.pixel(2, 27), Color::WHITE);
+}
+
+#[test]
+fn bounds_cover_strokes() {
+    let frame = Frame::new(shape(&[rect(10.0, 10.0, 10.0, 10.0)]), vec![Style { outline: Outline::Stroke, stroke_width: 2.0, ..Style::default() }], Color::BLACK);
+    // Half the stroke and a pixel for rounding on every side
+    assert_eq!(frame_bounds(&frame, 100, 100), Rect::new(8, 8, 14, 14));
+    // Clipped to the surface
+    assert_eq!(frame_bounds(&frame, 15, 100), Rect::new(8, 8, 7, 14));
+
+    let empty = Frame::new(Shape::new(), vec![], Color::BLACK);
+    assert!(frame_bounds(&empty, 100, 100).is_empty());
+}
+
+#[test]
+fn damage_covers_what_changed() {
+    let mut tracker = DamageTracker::new();
+    let before = filled(shape(&[rect(10.0, 10.0, 10.0, 10.0)]));
+    let after = filled(shape(&[rect(30.0, 20.0, 10.0, 10.0)]));
+
+    assert_eq!(tracker.update(&before, 100, 100), Damage::Full);
+    assert_eq!(tracker.update(&before, 100, 100), Damage::None);
+
+    // Where the path was and where it is now
+    let damage = tracker.update(&after, 100, 100);
+    let bounds = frame_bounds(&before, 100, 100).union(&frame_bounds(&after, 100, 100));
+    assert_eq!(damage, Damage::Partial(bounds));
+
+    // Anything that moves every pixel
+    assert_eq!(tracker.update(&after, 200, 100), Damage::Full);
+    let mut background = after.clone();
+    background.background = Color::WHITE;
+    assert_eq!(tracker.update(&background, 200, 100), Damage::Full);
+    tracker.reset();
+    assert_eq!(tracker.update(&background, 200, 100), Damage::Full);
+}
+
+#[test]
+fn partial_redraws_match_full_ones() {
+    let before = filled(shape(&[rect(10.0, 10.0, 10.0, 10.0), rect(50.0, 50.0, 20.0, 20.0)]));
+    let after = filled(shape(&[rect(30.0, 20.0, 10.0, 10.0), rect(50.0, 50.0, 20.0, 20.0)]));
+    let mut tracker = DamageTracker::new();
+    tracker.update(&before, 100, 100);
+    let Damage::Partial(region) = tracker.update(&after, 100, 100) else {
+        panic!("Expected partial damage");
+    };
+
+    let mut renderer = SoftwareRenderer::new();
+    let mut partial = render(&before, 100, 100);
+    let mut target = partial.buffer();
+    target.set_clip(region);
+    renderer.render(&after, &mut target);
+
+    // Nothing outside the damage was touched, yet the result is the same
+    assert_eq!(partial.data(), render(&after, 100, 100).data());
+}
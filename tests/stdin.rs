@@ -0,0 +1,163 @@
+mod common;
+
+use std::{
+    fs, io::{self, Write}, os::unix::net::UnixStream, sync::{Arc, Mutex}, time::{Duration, Instant}
+};
+
+use calloop::EventLoop;
+use common::{scratch_dir, test_face, TestFace};
+use hyogen_ui::{
+    animator::{easing::Easing, gaze::Gaze},
+    control::{protocol::{Command, Request}, stdin::StdinScript, ControlHandler},
+};
+use serde_json::Value;
+
+// Answers the script printed so far.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output {
+    fn lines(&self) -> Vec<Value> {
+        let output = self.0.lock().unwrap();
+        output.split(|byte| *byte == b'\n').filter(|line| !line.is_empty()).map(|line| serde_json::from_slice(line).unwrap()).collect()
+    }
+}
+
+// Run the loop until `count` answers were printed.
+fn answers(count: usize, output: &Output, event_loop: &mut EventLoop<'static, TestFace>, state: &mut TestFace) -> Vec<Value> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while output.lines().len() < count {
+        assert!(Instant::now() < deadline, "only got {:?}", output.lines());
+        event_loop.dispatch(Some(Duration::from_millis(5)), state).unwrap();
+    }
+    output.lines()
+}
+
+#[test]
+fn waits_hold_back_the_lines_after_them() {
+    let dir = scratch_dir("stdin-wait");
+    let mut state = test_face(&dir);
+    let mut event_loop: EventLoop<'static, TestFace> = EventLoop::try_new().unwrap();
+
+    let (mut input, script_input) = UnixStream::pair().unwrap();
+    let output = Output::default();
+    let script = StdinScript::with_io(script_input, output.clone());
+    script.attach(&event_loop.handle()).unwrap();
+
+    let start = Instant::now();
+    writeln!(input, r#"{{"cmd": "set", "expression": "happy", "duration": 0, "id": 1}}"#).unwrap();
+    writeln!(input, r#"{{"cmd": "wait", "duration": "200ms", "id": 2}}"#).unwrap();
+    writeln!(input, r#"{{"cmd": "set", "expression": "sad", "duration": 0, "id": 3}}"#).unwrap();
+
+    let lines = answers(2, &output, &mut event_loop, &mut state);
+    assert_eq!(lines[0]["id"].as_u64(), Some(1));
+    assert_eq!(lines[1]["id"].as_u64(), Some(2));
+    assert_eq!(state.face.expression, "happy");
+
+    // Still waiting a little later
+    event_loop.dispatch(Some(Duration::from_millis(50)), &mut state).unwrap();
+    if start.elapsed() < Duration::from_millis(200) {
+        assert_eq!(output.lines().len(), 2);
+        assert_eq!(state.face.expression, "happy");
+    }
+
+    let lines = answers(3, &output, &mut event_loop, &mut state);
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert_eq!(lines[2]["id"].as_u64(), Some(3));
+    assert_eq!(lines[2]["ok"].as_bool(), Some(true));
+    assert_eq!(state.face.expression, "sad");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn invalid_waits_are_rejected() {
+    let dir = scratch_dir("stdin-invalid");
+    let mut state = test_face(&dir);
+    let mut event_loop: EventLoop<'static, TestFace> = EventLoop::try_new().unwrap();
+
+    let (mut input, script_input) = UnixStream::pair().unwrap();
+    let output = Output::default();
+    let script = StdinScript::with_io(script_input, output.clone());
+    script.attach(&event_loop.handle()).unwrap();
+
+    for line in [
+        r#"{"cmd": "wait", "id": 1}"#,
+        r#"{"cmd": "wait", "duration": -1, "id": 2}"#,
+        r#"{"cmd": "wait", "duration": "later", "id": 3}"#,
+        r#"{"cmd": "wait", "duration": 1e20, "id": 4}"#,
+    ] {
+        writeln!(input, "{}", line).unwrap();
+    }
+    // Nothing is held back by them
+    writeln!(input, r#"{{"cmd": "set", "expression": "happy", "duration": 0, "id": 5}}"#).unwrap();
+
+    let start = Instant::now();
+    let lines = answers(5, &output, &mut event_loop, &mut state);
+    assert!(start.elapsed() < Duration::from_secs(1));
+    for (id, line) in lines[..4].iter().enumerate() {
+        assert_eq!(line["id"].as_u64(), Some(id as u64 + 1));
+        assert_eq!(line["ok"].as_bool(), Some(false), "{}", line);
+    }
+    assert_eq!(lines[4]["ok"].as_bool(), Some(true));
+    assert_eq!(state.face.expression, "happy");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn gaze_targets_are_clamped() {
+    let dir = scratch_dir("stdin-gaze");
+    let mut state = test_face(&dir);
+
+    let command = Request::parse(r#"{"cmd": "gaze", "x": 5, "y": -0.5, "duration": 0}"#).unwrap().command;
+    assert!(matches!(command, Command::Gaze { x, y, .. } if x == 5.0 && y == -0.5));
+    state.handle_command(command).unwrap();
+    assert_eq!(state.face.gaze.target(), (1.0, -0.5));
+
+    state.handle_command(Request::parse(r#"{"cmd": "gaze", "x": -2, "y": 3}"#).unwrap().command).unwrap();
+    assert_eq!(state.face.gaze.target(), (-1.0, 1.0));
+
+    for line in [
+        r#"{"cmd": "gaze", "x": "left", "y": 0}"#,
+        r#"{"cmd": "gaze", "y": 0}"#,
+        r#"{"cmd": "gaze", "x": 0, "y": null}"#,
+    ] {
+        assert!(Request::parse(line).is_err(), "{}", line);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn gaze_moves_over_time() {
+    let start = Instant::now();
+    let mut gaze = Gaze::default();
+    assert!(gaze.is_settled(start + Duration::from_millis(1)));
+
+    gaze.look_at((1.0, -1.0), Duration::from_secs(1), Easing::Linear, start);
+    assert_eq!(gaze.direction(start), (0.0, 0.0));
+    assert_eq!(gaze.direction(start + Duration::from_millis(500)), (0.5, -0.5));
+    assert!(!gaze.is_settled(start + Duration::from_millis(500)));
+    assert_eq!(gaze.direction(start + Duration::from_secs(2)), (1.0, -1.0));
+    assert!(gaze.is_settled(start + Duration::from_secs(1)));
+
+    // Looking elsewhere halfway starts from where the gaze is
+    let halfway = start + Duration::from_millis(500);
+    gaze.look_at((-1.0, 0.0), Duration::from_secs(1), Easing::Linear, halfway);
+    assert_eq!(gaze.direction(halfway), (0.5, -0.5));
+    assert_eq!(gaze.direction(halfway + Duration::from_millis(500)), (-0.25, -0.25));
+    assert_eq!(gaze.offset(halfway + Duration::from_secs(1)).1, 0.0);
+    assert!(gaze.offset(halfway + Duration::from_secs(1)).0 < 0.0);
+}
@@ -0,0 +1,47 @@
+use hyogen_ui::{geometry::Point, hvf::{loader::HVFLoader, svg}};
+
+#[test]
+fn shapes_become_closed_paths() {
+    let hvf = svg::import(r#"<?xml version="1.0"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="800" height="480">
+  <defs><rect x="0" y="0" width="800" height="480"/></defs>
+  <rect x="100" y="100" width="50" height="20"/>
+  <polygon points="10,10 20,10 20,20"/>
+  <g>
+    <path d="M 0 0 h 10 v 10 H 0 Z m 20 0 l 10 0 l 0 10 z"/>
+    <path d="M0 0Q10 10 20 0T40 0"/>
+  </g>
+  <circle cx="400" cy="240" r="10"/>
+</svg>"#).unwrap();
+
+    assert_eq!(hvf.values.len(), 6);
+    assert_eq!(hvf.values[0], [Point::new(100.0, 100.0), Point::new(150.0, 100.0), Point::new(150.0, 120.0), Point::new(100.0, 120.0)]);
+    assert_eq!(hvf.values[1], [Point::new(10.0, 10.0), Point::new(20.0, 10.0), Point::new(20.0, 20.0)]);
+    // Both subpaths, relative to where the last one started, without repeating the first point
+    assert_eq!(hvf.values[2], [Point::new(0.0, 0.0), Point::new(10.0, 0.0), Point::new(10.0, 10.0), Point::new(0.0, 10.0)]);
+    assert_eq!(hvf.values[3], [Point::new(20.0, 0.0), Point::new(30.0, 0.0), Point::new(30.0, 10.0)]);
+    // Curves are flattened, T mirrors the last control point
+    assert_eq!(hvf.values[4].len(), 17);
+    assert_eq!(hvf.values[4][8], Point::new(20.0, 0.0));
+    assert_eq!(hvf.values[4][12], Point::new(30.0, -5.0));
+    assert_eq!(hvf.values[5].len(), 32);
+
+    // Imported expressions make up a pack like any other
+    let mut pack = HVFLoader::default();
+    pack.insert("expression", "neutral", hvf);
+    assert_eq!(pack.ids("expression"), ["neutral"]);
+}
+
+#[test]
+fn unsupported_svg_is_an_error() {
+    for broken in [
+        "<svg></svg>",
+        r#"<svg><path d="M 0 0 A 5 5 0 0 1 10 10"/></svg>"#,
+        r#"<svg><g transform="scale(2)"><rect width="1" height="1"/></g></svg>"#,
+        r#"<svg><path d="10 10 L 20 20"/></svg>"#,
+        r#"<svg><circle r="ten"/></svg>"#,
+        "<svg><rect",
+    ] {
+        assert!(svg::import(broken).is_err(), "{}", broken);
+    }
+}